@@ -7,4 +7,7 @@
   # Note that this copies a bunch of stuff that's not actually needed by the game.
   cp -r media dist/ && \
   rm -rf dist/media/world/backups && \
+  if [ -n "$ANALYTICS_SERVER_ORIGIN" ]; then
+    printf '{\n    "server_origin": "%s"\n}\n' "$ANALYTICS_SERVER_ORIGIN" > dist/media/analytics.json
+  fi && \
   echo "Created WASM distribution in 'dist' directory."
@@ -2,6 +2,7 @@ import * as analyticsDialog from "./analytics-dialog.js";
 import * as audioDialog from "./audio-dialog.js";
 
 const config = await (await fetch("media/config.json")).json();
+const analyticsConfig = await (await fetch("media/analytics.json")).json();
 const width = config.screen_width * config.sprite_scale;
 const height = config.screen_height * config.sprite_scale;
 const canvas = document.getElementById("glcanvas");
@@ -28,17 +29,23 @@ window.addEventListener("resize", maybeScaleCanvas);
 
 maybeScaleCanvas();
 
-await analyticsDialog.maybeShowDialog();
+if (getServerOrigin()) {
+    await analyticsDialog.maybeShowDialog();
+}
 
 await audioDialog.maybeShowDialog();
 
 const DEBUG = false;
 
-const MIN_USEFUL_RECORDING_BYTES = 25;
+// Note that the recording's header, which contains metadata about the
+// build that made it, is usually around 50 bytes on its own.
+const MIN_USEFUL_RECORDING_BYTES = 75;
+
+const SAVE_SLOTS_KEY = "macroquad_fun_save_slots";
 
 const windowSearchParams = new URLSearchParams(window.location.search);
 
-const didUserConsentToAnalytics = analyticsDialog.hasUserGivenConsent();
+const didUserConsentToAnalytics = getServerOrigin() !== null && analyticsDialog.hasUserGivenConsent();
 
 const trackingTag = getAndCacheTrackingTag();
 
@@ -97,11 +104,15 @@ function areUsefulRecordingBytesAvailable() {
     return recordingBytes.toSend.length > minimumBytesToSend;
 }
 
+/**
+ * Returns the origin of the analytics server, or null if analytics
+ * are disabled.
+ */
 function getServerOrigin() {
     if (window.location.hostname === "localhost") {
         return "http://localhost:4001";
     }
-    return "https://macroquad-fun.toolness.org";
+    return analyticsConfig.server_origin || null;
 }
 
 async function sendRecordingBytes() {
@@ -191,6 +202,72 @@ miniquad_add_plugin({
             scheduleSendRecordingBytes(100);
         };
 
+        // The save slots are passed to the game in two steps: first it asks for
+        // their length so it can allocate enough memory, then it asks us to
+        // copy them into that memory.
+        let saveSlotsBytes = new Uint8Array();
+
+        importObject.env.get_save_slots_length = () => {
+            let saveSlots = null;
+            try {
+                saveSlots = window.localStorage.getItem(SAVE_SLOTS_KEY);
+            } catch (e) {
+            }
+            saveSlotsBytes = new TextEncoder().encode(saveSlots || "");
+            return saveSlotsBytes.length;
+        };
+
+        importObject.env.copy_save_slots = (ptr) => {
+            new Uint8Array(wasm_memory.buffer, ptr, saveSlotsBytes.length).set(saveSlotsBytes);
+        };
+
+        importObject.env.set_save_slots = (ptr, len) => {
+            const saveSlots = new TextDecoder().decode(new Uint8Array(wasm_memory.buffer, ptr, len));
+            try {
+                window.localStorage.setItem(SAVE_SLOTS_KEY, saveSlots);
+            } catch (e) {
+                console.error("Unable to save game", e);
+            }
+        };
+
+        // Only gamepads with the "standard" mapping are supported, since
+        // that's the only way we know which button is which:
+        // https://w3c.github.io/gamepad/#remapping
+        const getStandardGamepads = () => {
+            if (!navigator.getGamepads) {
+                return [];
+            }
+            return Array.from(navigator.getGamepads()).filter(
+                (gamepad) => gamepad && gamepad.mapping === "standard"
+            );
+        };
+
+        importObject.env.get_gamepad_buttons = () => {
+            let pressed = 0;
+            for (const gamepad of getStandardGamepads()) {
+                gamepad.buttons.slice(0, 32).forEach((button, i) => {
+                    if (button.pressed) {
+                        pressed |= 1 << i;
+                    }
+                });
+            }
+            return pressed;
+        };
+
+        importObject.env.get_gamepad_stick_x = () => {
+            let x = 0;
+            for (const gamepad of getStandardGamepads()) {
+                if (Math.abs(gamepad.axes[0]) > Math.abs(x)) {
+                    x = gamepad.axes[0];
+                }
+            }
+            return x;
+        };
+
+        importObject.env.is_touch_device = () => {
+            return navigator.maxTouchPoints > 0 || "ontouchstart" in window;
+        };
+
         importObject.env.init_version = (ptr) => {
             version = UTF8ToString(ptr);
             const throbber = document.getElementById("throbber");
@@ -0,0 +1,191 @@
+use std::{
+    fmt::Write,
+    fs,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use anyhow::{anyhow, Result};
+use macroquad_fun::{
+    headless::{load_headless, replay_recording, ReplaySummary},
+    recorder::Recording,
+    world::World,
+    CONFIG_PATH, WORLD_PATH,
+};
+use serde_derive::Serialize;
+
+use crate::recordings::RecordingFilename;
+
+/// The start position to use for recordings that don't specify one.
+const DEFAULT_START_POSITION: &str = "default";
+
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "analyze")]
+/// replay every stored recording and write a summary of their outcomes
+pub struct AnalyzeArgs {
+    #[argh(option, default = "String::from(\"..\")")]
+    /// root directory of the game, which contains its media directory
+    pub game_dir: String,
+
+    #[argh(option, default = "String::from(\"recording-summary.csv\")")]
+    /// filename to write the summary to, ending in either .csv or .json
+    pub output: String,
+}
+
+/// What happened in a single recording.
+#[derive(Serialize, Default)]
+struct RecordingAnalysis {
+    filename: String,
+    tracking_tag: String,
+    version: String,
+    uuid: String,
+    start_position: String,
+    frames: u64,
+    seconds: f64,
+    furthest_level: String,
+    levels_reached: usize,
+    gems_collected: usize,
+    picked_up_spear: bool,
+    mushrooms_rezzed: usize,
+    deaths: u32,
+    desync_frame: Option<u64>,
+    error: Option<String>,
+}
+
+impl RecordingAnalysis {
+    fn set_summary(&mut self, summary: ReplaySummary) {
+        self.frames = summary.frames;
+        self.seconds = summary.seconds();
+        self.furthest_level = summary
+            .outcomes
+            .furthest_level()
+            .unwrap_or_default()
+            .to_owned();
+        self.levels_reached = summary.outcomes.levels_reached();
+        self.gems_collected = summary.outcomes.gems_collected();
+        self.picked_up_spear = summary.outcomes.picked_up_spear();
+        self.mushrooms_rezzed = summary.outcomes.mushrooms_rezzed();
+        self.deaths = summary.deaths;
+        self.desync_frame = summary.first_desync.map(|desync| desync.frame_number);
+    }
+}
+
+const CSV_HEADER: &str = "filename,tracking_tag,version,uuid,start_position,frames,seconds,furthest_level,levels_reached,gems_collected,picked_up_spear,mushrooms_rezzed,deaths,desync_frame,error";
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+fn to_csv(analyses: &[RecordingAnalysis]) -> String {
+    let mut csv = String::new();
+    writeln!(csv, "{}", CSV_HEADER).unwrap();
+    for a in analyses {
+        writeln!(
+            csv,
+            "{},{},{},{},{},{},{:.2},{},{},{},{},{},{},{},{}",
+            csv_escape(&a.filename),
+            csv_escape(&a.tracking_tag),
+            csv_escape(&a.version),
+            a.uuid,
+            csv_escape(&a.start_position),
+            a.frames,
+            a.seconds,
+            csv_escape(&a.furthest_level),
+            a.levels_reached,
+            a.gems_collected,
+            a.picked_up_spear,
+            a.mushrooms_rezzed,
+            a.deaths,
+            a.desync_frame.map(|f| f.to_string()).unwrap_or_default(),
+            csv_escape(a.error.as_deref().unwrap_or_default())
+        )
+        .unwrap();
+    }
+    csv
+}
+
+fn replay(bytes: &[u8], analysis: &mut RecordingAnalysis, world: &Rc<World>) -> Result<()> {
+    let recording = Recording::parse(bytes)?;
+    analysis.start_position = match &recording.metadata {
+        Some(metadata) => metadata.start_position.clone(),
+        None => DEFAULT_START_POSITION.to_owned(),
+    };
+    // The simulation panics on some kinds of bad input (e.g. a start position
+    // that doesn't exist), and we don't want one bad recording to stop us from
+    // analyzing the rest.
+    let summary = catch_unwind(AssertUnwindSafe(|| {
+        replay_recording(recording, &analysis.start_position, world.clone())
+    }))
+    .map_err(|_| anyhow!("Simulation panicked"))??;
+    analysis.set_summary(summary);
+    Ok(())
+}
+
+fn analyze_recording(
+    path: &Path,
+    filename: RecordingFilename,
+    world: &Rc<World>,
+) -> RecordingAnalysis {
+    let mut analysis = RecordingAnalysis {
+        filename: filename.to_string(),
+        tracking_tag: filename.tracking_tag,
+        version: filename.version,
+        uuid: filename.uuid.to_string(),
+        ..Default::default()
+    };
+    let result = fs::read(path)
+        .map_err(|e| e.into())
+        .and_then(|bytes| replay(&bytes, &mut analysis, world));
+    if let Err(e) = result {
+        eprintln!("Error analyzing {}: {}", analysis.filename, e);
+        analysis.error = Some(e.to_string());
+    }
+    analysis
+}
+
+pub fn run_analysis(args: &AnalyzeArgs, recordings_dir: &Path) -> Result<()> {
+    // The game loads its assets from paths relative to its root directory, so we'll
+    // need to change to it, which means we need absolute paths for everything else.
+    let cwd = std::env::current_dir()?;
+    let recordings_dir = cwd.join(recordings_dir);
+    let output = cwd.join(&args.output);
+    std::env::set_current_dir(&args.game_dir)?;
+    let world = load_headless(CONFIG_PATH, WORLD_PATH)?;
+
+    let mut paths: Vec<(PathBuf, RecordingFilename)> = vec![];
+    for entry in fs::read_dir(&recordings_dir)? {
+        let path = entry?.path();
+        let filename = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .unwrap_or_default();
+        if let Some(recording_filename) = RecordingFilename::parse(filename) {
+            paths.push((path, recording_filename));
+        }
+    }
+    paths.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut analyses = Vec::with_capacity(paths.len());
+    for (path, filename) in paths {
+        println!("Analyzing {}.", filename);
+        analyses.push(analyze_recording(&path, filename, &world));
+    }
+
+    let summary = if output.extension().and_then(|ext| ext.to_str()) == Some("json") {
+        serde_json::to_string_pretty(&analyses)?
+    } else {
+        to_csv(&analyses)
+    };
+    fs::write(&output, summary)?;
+    println!(
+        "Wrote summary of {} recording(s) to {}.",
+        analyses.len(),
+        output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use std::{
+    collections::HashMap,
+    fs,
+    net::IpAddr,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How long the rate limiter's window is.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Once the rate limiter is tracking this many clients, it will forget
+/// about the ones whose windows have expired.
+const RATE_LIMIT_PRUNE_THRESHOLD: usize = 1000;
+
+/// Limits how many requests each IP address can make per minute.
+pub struct RateLimiter {
+    max_requests_per_minute: u32,
+    clients: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_requests_per_minute: u32) -> Self {
+        RateLimiter {
+            max_requests_per_minute,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a request from the given IP address, returning whether it's
+    /// within the rate limit.
+    pub fn check(&self, ip: IpAddr, now: Instant) -> bool {
+        let mut clients = self.clients.lock().unwrap();
+        if clients.len() >= RATE_LIMIT_PRUNE_THRESHOLD {
+            clients.retain(|_, (window_start, _)| now - *window_start < RATE_LIMIT_WINDOW);
+        }
+        let (window_start, requests) = clients.entry(ip).or_insert((now, 0));
+        if now - *window_start >= RATE_LIMIT_WINDOW {
+            *window_start = now;
+            *requests = 0;
+        }
+        if *requests >= self.max_requests_per_minute {
+            return false;
+        }
+        *requests += 1;
+        true
+    }
+}
+
+/// Keeps track of how much disk space all our recordings take up.
+pub struct StorageQuota {
+    max_bytes: u64,
+    used_bytes: Mutex<u64>,
+}
+
+impl StorageQuota {
+    pub fn new(max_bytes: u64, used_bytes: u64) -> Self {
+        StorageQuota {
+            max_bytes,
+            used_bytes: Mutex::new(used_bytes),
+        }
+    }
+
+    /// Create a quota whose usage is the total size of the files in the
+    /// given directory.
+    pub fn for_dir(max_bytes: u64, path: &Path) -> Result<Self, std::io::Error> {
+        let mut used_bytes = 0;
+        for entry in fs::read_dir(path)? {
+            let metadata = entry?.metadata()?;
+            if metadata.is_file() {
+                used_bytes += metadata.len();
+            }
+        }
+        Ok(StorageQuota::new(max_bytes, used_bytes))
+    }
+
+    pub fn used_bytes(&self) -> u64 {
+        *self.used_bytes.lock().unwrap()
+    }
+
+    /// Attempt to reserve the given number of bytes, returning whether there
+    /// was enough room for them.
+    pub fn try_reserve(&self, bytes: u64) -> bool {
+        let mut used_bytes = self.used_bytes.lock().unwrap();
+        if *used_bytes + bytes > self.max_bytes {
+            return false;
+        }
+        *used_bytes += bytes;
+        true
+    }
+
+    /// Give back bytes that were reserved but never actually used.
+    pub fn release(&self, bytes: u64) {
+        let mut used_bytes = self.used_bytes.lock().unwrap();
+        *used_bytes = used_bytes.saturating_sub(bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        time::{Duration, Instant},
+    };
+
+    use super::{RateLimiter, StorageQuota};
+
+    #[test]
+    fn test_rate_limiter_works() {
+        let limiter = RateLimiter::new(2);
+        let alice = IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4));
+        let bob = IpAddr::V4(Ipv4Addr::new(5, 6, 7, 8));
+        let now = Instant::now();
+        assert!(limiter.check(alice, now));
+        assert!(limiter.check(alice, now));
+        assert!(!limiter.check(alice, now));
+        assert!(limiter.check(bob, now));
+        assert!(limiter.check(alice, now + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn test_storage_quota_works() {
+        let quota = StorageQuota::new(10, 4);
+        assert!(quota.try_reserve(6));
+        assert!(!quota.try_reserve(1));
+        quota.release(3);
+        assert_eq!(quota.used_bytes(), 7);
+        assert!(quota.try_reserve(3));
+    }
+}
@@ -1,10 +1,65 @@
-use std::{path::Path, borrow::Cow, fs, io::Write};
+use std::{path::{Path, PathBuf}, borrow::Cow, fs, io::Write, net::IpAddr, time::Instant};
 
+use analyze::{run_analysis, AnalyzeArgs};
+use limits::{RateLimiter, StorageQuota};
+use macroquad_fun::recorder::Recording;
+use read_endpoints::{check_authorization, download_recording, list_recordings};
+use recordings::RecordingFilename;
 use rouille::{Response, try_or_400, post_input, input::post::BufferedFile, Request};
 use semver::Version;
 use uuid::Uuid;
 
-const RECORDINGS_DIR: &'static str = "recordings";
+mod analyze;
+mod limits;
+mod read_endpoints;
+mod recordings;
+
+const DEFAULT_RECORDINGS_DIR: &str = "recordings";
+
+const DEFAULT_ADDR: &str = "0.0.0.0:4001";
+
+/// Environment variable containing the token needed to read recordings.
+const READ_TOKEN_ENV_VAR: &str = "MACROQUAD_FUN_READ_TOKEN";
+
+/// File containing the token needed to read recordings, if the environment
+/// variable isn't set.
+const READ_TOKEN_FILE: &str = "read-token.txt";
+
+struct ServerConfig {
+    recordings_dir: PathBuf,
+    /// The origins that are allowed to make cross-origin requests. If empty,
+    /// any origin is allowed.
+    cors_origins: Vec<String>,
+    /// The token clients need to provide to read recordings. If `None`, no
+    /// one can read recordings.
+    read_token: Option<String>,
+    /// The maximum size of a single recording, in bytes.
+    max_recording_size: u64,
+    /// Whether to identify clients by the last address in the `X-Forwarded-For`
+    /// header, which should only be done if we're behind a reverse proxy.
+    trust_forwarded_for: bool,
+    rate_limiter: RateLimiter,
+    storage_quota: StorageQuota,
+}
+
+/// Returns the value of the given environment variable, or the given default if
+/// it isn't set.
+fn env_or(name: &str, default: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| default.to_owned())
+}
+
+fn load_read_token() -> Option<String> {
+    let token = match std::env::var(READ_TOKEN_ENV_VAR) {
+        Ok(token) => token,
+        Err(_) => fs::read_to_string(READ_TOKEN_FILE).ok()?,
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        None
+    } else {
+        Some(token.to_owned())
+    }
+}
 
 fn nonempty_string(value: &Option<String>) -> &Option<String> {
     if let Some(value_string) = value {
@@ -51,14 +106,69 @@ fn ensure_dir_exists(path: &Path) -> Result<(), std::io::Error> {
     Ok(())
 }
 
-fn process_request(request: &Request) -> Response {
+/// Returns the IP address of the client that made the given request.
+fn client_ip(request: &Request, config: &ServerConfig) -> IpAddr {
+    if config.trust_forwarded_for {
+        // Our reverse proxy appends the address it received the request from, so
+        // the last one is the only one we can trust.
+        let forwarded_ip = request
+            .header("X-Forwarded-For")
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+        if let Some(ip) = forwarded_ip {
+            return ip;
+        }
+    }
+    request.remote_addr().ip()
+}
+
+/// Returns the value of the `Access-Control-Allow-Origin` header to send in
+/// response to the given request, if any.
+fn cors_allow_origin(request: &Request, config: &ServerConfig) -> Option<String> {
+    if config.cors_origins.is_empty() {
+        return Some("*".to_owned());
+    }
+    let origin = request.header("Origin")?;
+    if config.cors_origins.iter().any(|allowed| allowed == origin) {
+        Some(origin.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Process a request that reads recordings, making sure it's authorized first.
+fn process_read_request<F: FnOnce() -> Response>(
+    request: &Request,
+    config: &ServerConfig,
+    f: F,
+) -> Response {
+    if request.method() != "GET" {
+        return Response::text("Method Not Allowed").with_status_code(405);
+    }
+    if let Some(response) = check_authorization(request, &config.read_token) {
+        return response;
+    }
+    f()
+}
+
+fn process_request(request: &Request, config: &ServerConfig) -> Response {
+    let recordings_dir = config.recordings_dir.as_path();
     match request.url().as_str() {
         "/" => Response::text("hi!"),
+        "/recordings" => process_read_request(request, config, || {
+            list_recordings(request, recordings_dir)
+        }),
+        url if url.starts_with("/recordings/") => process_read_request(request, config, || {
+            download_recording(&url["/recordings/".len()..], recordings_dir)
+        }),
         "/record" => {
             if request.method() == "POST" {
                 // This is prone to abuse in all kinds of ways, but since we don't allow clients to
-                // read data that they've submitted, the only thing they can really do is fill up
-                // our disk storage.
+                // read data that they've submitted (unless they have the read token), the main
+                // thing they can do is fill up our disk storage, which our limits guard against.
+                if !config.rate_limiter.check(client_ip(request, config), Instant::now()) {
+                    return Response::text("Too Many Requests").with_status_code(429);
+                }
                 let input = try_or_400!(
                     // b = bytes to append to recording
                     // p = previous length of recording, in bytes (0 if it's a new recording)
@@ -68,7 +178,13 @@ fn process_request(request: &Request) -> Response {
                     post_input!(request, { b: BufferedFile, v: String, t: Option<String>, p: usize, id: Option<String> })
                 );
                 let bytes = &input.b.data;
+                if bytes.is_empty() {
+                    return Response::text("Missing bytes").with_status_code(400);
+                }
                 let prev_len = input.p;
+                if prev_len as u64 + bytes.len() as u64 > config.max_recording_size {
+                    return Response::text("Recording too large").with_status_code(413);
+                }
                 if prev_len > 0 && input.id.is_none() {
                     return Response::text("Missing ID").with_status_code(400);
                 }
@@ -85,29 +201,37 @@ fn process_request(request: &Request) -> Response {
                     Cow::Borrowed("anonymous")
                 };
                 let uuid = try_or_400!(parse_uuid_string(&input.id)).unwrap_or(Uuid::new_v4());
-                let filename = format!("{}-{}-{}.bin", tracking_tag, version, uuid);
-                let path = Path::new(RECORDINGS_DIR).join(filename.clone());
+                let filename = RecordingFilename::new(&tracking_tag, version, uuid).to_string();
+                let path = recordings_dir.join(filename.clone());
 
-                if !path.exists() {
-                    let Ok(_) = fs::write(&path, vec![]) else {
-                        eprintln!("Error creating file: {}", path.display());
+                let existing_bytes = if path.exists() {
+                    let Ok(existing_bytes) = fs::read(&path) else {
+                        eprintln!("Error reading file: {}", path.display());
                         return Response::text("Internal Server Error").with_status_code(500);
                     };
-                    #[cfg(debug_assertions)]
-                    println!("Created {}.", filename);
-                }
-
-                let Ok(metadata) = fs::metadata(&path) else {
-                    eprintln!("Error getting metadata for file: {}", path.display());
-                    return Response::text("Internal Server Error").with_status_code(500);
+                    existing_bytes
+                } else {
+                    vec![]
                 };
 
-                if metadata.len() == prev_len as u64 {
-                    let Ok(mut file) = fs::OpenOptions::new().append(true).open(&path) else {
+                if existing_bytes.len() == prev_len {
+                    // Clients always send whole events, so the recording should still be
+                    // decodable once the new bytes are appended to it.
+                    let mut recording_bytes = existing_bytes;
+                    recording_bytes.extend_from_slice(bytes);
+                    if Recording::parse(&recording_bytes).is_err() {
+                        return Response::text("Invalid recording data").with_status_code(400);
+                    }
+                    if !config.storage_quota.try_reserve(bytes.len() as u64) {
+                        return Response::text("Storage quota exceeded").with_status_code(413);
+                    }
+                    let Ok(mut file) = fs::OpenOptions::new().create(true).append(true).open(&path) else {
+                        config.storage_quota.release(bytes.len() as u64);
                         eprintln!("Error opening file for appending: {}", path.display());
                         return Response::text("Internal Server Error").with_status_code(500);
                     };
                     let Ok(_) = file.write_all(&bytes) else {
+                        config.storage_quota.release(bytes.len() as u64);
                         eprintln!("Error writing to file: {}", path.display());
                         return Response::text("Internal Server Error").with_status_code(500);
                     };
@@ -131,15 +255,109 @@ fn process_request(request: &Request) -> Response {
     }
 }
 
+#[derive(argh::FromArgs)]
+/// macroquad-fun analytics server
+struct Cli {
+    #[argh(option, default = "env_or(\"MACROQUAD_FUN_ADDR\", DEFAULT_ADDR)")]
+    /// address to listen on (defaults to $MACROQUAD_FUN_ADDR or 0.0.0.0:4001)
+    addr: String,
+
+    #[argh(option, default = "env_or(\"MACROQUAD_FUN_RECORDINGS_DIR\", DEFAULT_RECORDINGS_DIR).into()")]
+    /// directory to store recordings in (defaults to $MACROQUAD_FUN_RECORDINGS_DIR or "recordings")
+    recordings_dir: PathBuf,
+
+    #[argh(option)]
+    /// origin allowed to make cross-origin requests, e.g. https://example.com; can be repeated
+    /// (defaults to the comma-separated $MACROQUAD_FUN_CORS_ORIGINS, or any origin if that isn't set)
+    cors_origin: Vec<String>,
+
+    #[argh(option, default = "1_000_000")]
+    /// maximum size of a single recording, in bytes
+    max_recording_size: u64,
+
+    #[argh(option, default = "1_000_000_000")]
+    /// maximum total size of all recordings, in bytes
+    storage_quota: u64,
+
+    #[argh(option, default = "120")]
+    /// maximum number of recording submissions per minute from a single IP address
+    max_requests_per_minute: u32,
+
+    #[argh(switch)]
+    /// identify clients by the X-Forwarded-For header (only use this behind a reverse proxy)
+    trust_forwarded_for: bool,
+
+    #[argh(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(argh::FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Analyze(AnalyzeArgs),
+}
+
 fn main() {
-    let addr: &'static str = "0.0.0.0:4001";
+    let args: Cli = argh::from_env();
+
+    if let Some(Command::Analyze(analyze_args)) = &args.command {
+        run_analysis(analyze_args, &args.recordings_dir).unwrap();
+        return;
+    }
+
+    ensure_dir_exists(&args.recordings_dir).unwrap();
+
+    let mut cors_origins = args.cors_origin;
+    if cors_origins.is_empty() {
+        if let Ok(origins) = std::env::var("MACROQUAD_FUN_CORS_ORIGINS") {
+            cors_origins = origins
+                .split(',')
+                .map(|origin| origin.trim().to_owned())
+                .filter(|origin| !origin.is_empty())
+                .collect();
+        }
+    }
 
-    ensure_dir_exists(Path::new(RECORDINGS_DIR)).unwrap();
+    let config = ServerConfig {
+        cors_origins,
+        read_token: load_read_token(),
+        max_recording_size: args.max_recording_size,
+        trust_forwarded_for: args.trust_forwarded_for,
+        rate_limiter: RateLimiter::new(args.max_requests_per_minute),
+        storage_quota: StorageQuota::for_dir(args.storage_quota, &args.recordings_dir).unwrap(),
+        recordings_dir: args.recordings_dir,
+    };
+    println!(
+        "Recordings are using {} of {} bytes.",
+        config.storage_quota.used_bytes(),
+        args.storage_quota
+    );
+    if config.read_token.is_none() {
+        println!(
+            "No read token found in ${} or {}, so reading recordings is disabled.",
+            READ_TOKEN_ENV_VAR, READ_TOKEN_FILE
+        );
+    }
 
-    println!("Starting HTTP server on {}.", addr);
+    if config.cors_origins.is_empty() {
+        println!("Allowing cross-origin requests from any origin.");
+    } else {
+        println!("Allowing cross-origin requests from: {}", config.cors_origins.join(", "));
+    }
 
-    rouille::start_server(addr, move |request| {
-        let response = process_request(request);
-        response.with_additional_header("Access-Control-Allow-Origin", "*")
+    println!("Starting HTTP server on {}.", args.addr);
+
+    rouille::start_server(args.addr, move |request| {
+        let response = process_request(request, &config);
+        let response = match cors_allow_origin(request, &config) {
+            Some(origin) => response.with_additional_header("Access-Control-Allow-Origin", origin),
+            None => response,
+        };
+        if config.cors_origins.is_empty() {
+            response
+        } else {
+            // Our response depends on the request's origin, so caches need to know that.
+            response.with_additional_header("Vary", "Origin")
+        }
     });
 }
@@ -0,0 +1,201 @@
+use std::{fs, path::Path, time::UNIX_EPOCH};
+
+use rouille::{Request, Response};
+use serde_derive::Serialize;
+use uuid::Uuid;
+
+use crate::recordings::RecordingFilename;
+
+const SECONDS_PER_DAY: u64 = 60 * 60 * 24;
+
+/// Information about a stored recording, as returned by the listing endpoint.
+#[derive(Serialize)]
+struct RecordingInfo {
+    filename: String,
+    tracking_tag: String,
+    version: String,
+    uuid: Uuid,
+    size: u64,
+    /// When the recording was last modified, in seconds since the Unix epoch.
+    modified: u64,
+    /// The UTC date the recording was last modified, in YYYY-MM-DD format.
+    date: String,
+}
+
+/// Convert a date in YYYY-MM-DD format to the number of days since the Unix epoch.
+///
+/// This uses the algorithm from http://howardhinnant.github.io/date_algorithms.html.
+fn parse_date(value: &str) -> Option<i64> {
+    let mut parts = value.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+/// The inverse of `parse_date()`.
+fn format_date(days_since_epoch: i64) -> String {
+    let z = days_since_epoch + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Compare two strings in an amount of time that only depends on their lengths,
+/// so that the token can't be guessed via a timing attack.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.bytes()
+        .zip(b.bytes())
+        .fold(0, |acc, (x, y)| acc | (x ^ y))
+        == 0
+}
+
+/// Returns an error response if the request isn't authorized to read recordings.
+pub fn check_authorization(request: &Request, read_token: &Option<String>) -> Option<Response> {
+    let Some(read_token) = read_token else {
+        return Some(Response::text("Reading recordings is disabled").with_status_code(403));
+    };
+    let provided_token = request
+        .header("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match provided_token {
+        Some(token) if constant_time_eq(token, read_token) => None,
+        _ => Some(
+            Response::text("Unauthorized")
+                .with_status_code(401)
+                .with_additional_header("WWW-Authenticate", "Bearer"),
+        ),
+    }
+}
+
+fn get_date_param(request: &Request, name: &str) -> Result<Option<i64>, Response> {
+    match request.get_param(name) {
+        Some(value) => match parse_date(&value) {
+            Some(days) => Ok(Some(days)),
+            None => Err(Response::text(format!("Invalid {} date", name)).with_status_code(400)),
+        },
+        None => Ok(None),
+    }
+}
+
+/// List recordings, optionally filtered by the `tag`, `version`, `since` and `until`
+/// querystring arguments. Dates are in YYYY-MM-DD format, and are inclusive.
+pub fn list_recordings(request: &Request, recordings_dir: &Path) -> Response {
+    let tag = request.get_param("tag");
+    let version = request.get_param("version");
+    let since = match get_date_param(request, "since") {
+        Ok(since) => since,
+        Err(response) => return response,
+    };
+    let until = match get_date_param(request, "until") {
+        Ok(until) => until,
+        Err(response) => return response,
+    };
+
+    let Ok(entries) = fs::read_dir(recordings_dir) else {
+        eprintln!("Error reading directory: {}", recordings_dir.display());
+        return Response::text("Internal Server Error").with_status_code(500);
+    };
+    let mut recordings: Vec<RecordingInfo> = vec![];
+    for entry in entries.flatten() {
+        let filename = entry.file_name().to_string_lossy().into_owned();
+        let Some(parsed) = RecordingFilename::parse(&filename) else {
+            continue;
+        };
+        if tag.as_ref().is_some_and(|tag| tag != &parsed.tracking_tag)
+            || version.as_ref().is_some_and(|v| v != &parsed.version)
+        {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let days = (modified / SECONDS_PER_DAY) as i64;
+        if since.is_some_and(|since| days < since) || until.is_some_and(|until| days > until) {
+            continue;
+        }
+        recordings.push(RecordingInfo {
+            filename,
+            tracking_tag: parsed.tracking_tag,
+            version: parsed.version,
+            uuid: parsed.uuid,
+            size: metadata.len(),
+            modified,
+            date: format_date(days),
+        });
+    }
+    recordings.sort_by_key(|recording| recording.modified);
+    Response::json(&recordings)
+}
+
+/// Download the recording with the given UUID.
+pub fn download_recording(uuid: &str, recordings_dir: &Path) -> Response {
+    let Ok(uuid) = Uuid::parse_str(uuid) else {
+        return Response::text("Invalid UUID").with_status_code(400);
+    };
+    let Ok(entries) = fs::read_dir(recordings_dir) else {
+        eprintln!("Error reading directory: {}", recordings_dir.display());
+        return Response::text("Internal Server Error").with_status_code(500);
+    };
+    for entry in entries.flatten() {
+        let filename = entry.file_name().to_string_lossy().into_owned();
+        if RecordingFilename::parse(&filename).map(|parsed| parsed.uuid) != Some(uuid) {
+            continue;
+        }
+        let Ok(bytes) = fs::read(entry.path()) else {
+            eprintln!("Error reading file: {}", entry.path().display());
+            return Response::text("Internal Server Error").with_status_code(500);
+        };
+        return Response::from_data("application/octet-stream", bytes).with_additional_header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", filename),
+        );
+    }
+    Response::empty_404()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{constant_time_eq, format_date, parse_date};
+
+    #[test]
+    fn test_dates_work() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2023-03-01"), Some(19417));
+        assert_eq!(format_date(19417), "2023-03-01");
+        assert_eq!(format_date(parse_date("2024-02-29").unwrap()), "2024-02-29");
+        assert_eq!(parse_date("2023-13-01"), None);
+        assert_eq!(parse_date("blah"), None);
+    }
+
+    #[test]
+    fn test_constant_time_eq_works() {
+        assert!(constant_time_eq("boop", "boop"));
+        assert!(!constant_time_eq("boop", "bop"));
+        assert!(!constant_time_eq("boop", "beep"));
+    }
+}
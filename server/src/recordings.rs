@@ -0,0 +1,64 @@
+use uuid::Uuid;
+
+/// The information encoded in the filename of a stored recording.
+#[derive(Debug, PartialEq)]
+pub struct RecordingFilename {
+    pub tracking_tag: String,
+    pub version: String,
+    pub uuid: Uuid,
+}
+
+impl RecordingFilename {
+    pub fn new(tracking_tag: &str, version: &str, uuid: Uuid) -> Self {
+        RecordingFilename {
+            tracking_tag: tracking_tag.to_owned(),
+            version: version.to_owned(),
+            uuid,
+        }
+    }
+
+    /// Parse a filename created by `to_string()`. Returns `None` if the
+    /// filename isn't that of a recording.
+    pub fn parse(filename: &str) -> Option<Self> {
+        let stem = filename.strip_suffix(".bin")?;
+        // Tracking tags are alphanumeric, so they can't contain dashes, and UUIDs are
+        // always the same length. Versions can contain dashes, though.
+        let (tracking_tag, rest) = stem.split_once('-')?;
+        let uuid_start = rest.len().checked_sub(Uuid::nil().to_string().len() + 1)?;
+        let (version, uuid) = rest.split_at(uuid_start);
+        let uuid = Uuid::parse_str(uuid.strip_prefix('-')?).ok()?;
+        Some(RecordingFilename::new(tracking_tag, version, uuid))
+    }
+}
+
+impl std::fmt::Display for RecordingFilename {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}-{}.bin",
+            self.tracking_tag, self.version, self.uuid
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::RecordingFilename;
+
+    #[test]
+    fn test_recording_filenames_round_trip() {
+        let filename = RecordingFilename::new("boop", "1.0.0-beta.1", Uuid::new_v4());
+        assert_eq!(
+            RecordingFilename::parse(&filename.to_string()),
+            Some(filename)
+        );
+    }
+
+    #[test]
+    fn test_non_recording_filenames_are_rejected() {
+        assert_eq!(RecordingFilename::parse("blah.bin"), None);
+        assert_eq!(RecordingFilename::parse("summary.csv"), None);
+    }
+}
@@ -1,8 +1,8 @@
 use macroquad::prelude::clamp;
 
-use crate::{config::config, time::GameTime};
+use crate::{config::Config, time::GameTime};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Animator {
     last_frame: u32,
     is_reversed: bool,
@@ -11,12 +11,12 @@ pub struct Animator {
 }
 
 impl Animator {
-    pub fn new(last_frame: u32, is_reversed: bool, time: &GameTime) -> Self {
+    pub fn new(last_frame: u32, is_reversed: bool, time: &GameTime, config: &Config) -> Self {
         Animator {
             last_frame,
             is_reversed,
             start_time: time.now,
-            ms_per_animation_frame: config().ms_per_animation_frame,
+            ms_per_animation_frame: config.ms_per_animation_frame,
         }
     }
 
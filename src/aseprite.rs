@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use anyhow::Result;
 use macroquad::prelude::*;
 
-use crate::config::config;
+use crate::asset_loader::AssetLoader;
 
 extern crate serde_derive;
 
@@ -36,11 +36,14 @@ pub struct Bound {
     pub h: f32,
 }
 
-pub async fn load_aseprite_slices(path: &str) -> Result<HashMap<String, Rect>> {
-    let json_string = load_string(&path).await?;
+pub async fn load_aseprite_slices(
+    loader: &AssetLoader,
+    path: &str,
+    scale: f32,
+) -> Result<HashMap<String, Rect>> {
+    let json_string = loader.load_string(path).await?;
     let aseprite: Aseprite = serde_json::from_str(json_string.as_str())?;
     let mut slices = HashMap::with_capacity(aseprite.meta.slices.len());
-    let scale = config().sprite_scale;
 
     for slice in aseprite.meta.slices {
         let name = slice.name;
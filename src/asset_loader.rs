@@ -0,0 +1,94 @@
+use anyhow::Result;
+use macroquad::{
+    prelude::load_string,
+    texture::{load_image, load_texture, FilterMode, Image, Texture2D},
+};
+
+use crate::{
+    audio::{load_sound_effect, SoundEffect},
+    game_assets::SpriteRendererId,
+    sprite_renderer::SpriteRenderer,
+};
+
+/// Determines how we load assets from disk.
+///
+/// Macroquad's loading functions need a window (and, for textures, a GL
+/// context), so when we're running headless we read files directly from the
+/// filesystem instead, and stub out anything that only matters for
+/// rendering or audio.
+///
+/// Note that in headless mode, none of the async methods ever actually
+/// wait on anything, so they can be driven by a trivial executor.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AssetLoader {
+    Macroquad,
+    Headless,
+}
+
+impl AssetLoader {
+    pub fn is_headless(&self) -> bool {
+        *self == AssetLoader::Headless
+    }
+
+    pub async fn load_string(&self, path: &str) -> Result<String> {
+        match self {
+            AssetLoader::Macroquad => Ok(load_string(path).await?),
+            AssetLoader::Headless => Ok(std::fs::read_to_string(path)?),
+        }
+    }
+
+    pub async fn load_image(&self, path: &str) -> Result<Image> {
+        match self {
+            AssetLoader::Macroquad => Ok(load_image(path).await?),
+            AssetLoader::Headless => Ok(Image::from_file_with_format(&std::fs::read(path)?, None)),
+        }
+    }
+
+    /// Load a texture that will be rendered without any smoothing. In headless
+    /// mode, this just returns an empty texture.
+    pub async fn load_pixel_perfect_texture(&self, path: &str) -> Result<Texture2D> {
+        match self {
+            AssetLoader::Macroquad => {
+                let texture = load_texture(path).await?;
+                texture.set_filter(FilterMode::Nearest);
+                Ok(texture)
+            }
+            AssetLoader::Headless => Ok(Texture2D::empty()),
+        }
+    }
+
+    /// Load a sprite sheet with the given number of horizontally-arranged frames.
+    ///
+    /// In headless mode we still need to know the dimensions of the sprite's frames,
+    /// since they're used to compute bounding boxes, so the image is decoded on the
+    /// CPU but never uploaded to the GPU.
+    pub async fn load_sprite_renderer(
+        &self,
+        id: SpriteRendererId,
+        path: &str,
+        num_frames: u32,
+        scale: f32,
+    ) -> Result<SpriteRenderer> {
+        match self {
+            AssetLoader::Macroquad => Ok(SpriteRenderer::new(
+                id,
+                load_texture(path).await?,
+                num_frames,
+                scale,
+            )),
+            AssetLoader::Headless => Ok(SpriteRenderer::new_headless(
+                id,
+                &self.load_image(path).await?,
+                num_frames,
+                scale,
+            )),
+        }
+    }
+
+    pub async fn load_sound_effect(&self, path: &'static str) -> Result<SoundEffect> {
+        match self {
+            AssetLoader::Macroquad => Ok(load_sound_effect(path).await?),
+            AssetLoader::Headless => Ok(None),
+        }
+    }
+}
@@ -1,10 +1,10 @@
 use macroquad::prelude::{Rect, Vec2};
 
 use crate::{
-    audio::play_sound_effect,
-    config::config,
+    config::Config,
+    context::GameContext,
     entity::{filter_and_process_entities, Entity, EntityMap},
-    game_assets::game_assets,
+    events::GameEvent,
     level::Level,
     physics::PhysicsComponent,
     sprite_component::SpriteComponent,
@@ -13,7 +13,13 @@ use crate::{
 
 const CARRY_Y_OFFSET: f32 = 10.0;
 
-pub fn attachment_system(entities: &mut EntityMap, level: &Level, time: &GameTime) {
+pub fn attachment_system(
+    entities: &mut EntityMap,
+    events: &mut Vec<GameEvent>,
+    level: &Level,
+    time: &GameTime,
+    ctx: &GameContext,
+) {
     filter_and_process_entities(
         entities,
         |entity| {
@@ -23,9 +29,10 @@ pub fn attachment_system(entities: &mut EntityMap, level: &Level, time: &GameTim
                 false
             }
         },
-        |entity, entities, _| {
+        |entity, entities, id| {
             let sprite = &mut entity.sprite;
             let attachment = entity.attachment.as_mut().unwrap();
+            let was_attached_to = attachment.attached_to_entity_id();
             if let Some(carrier_entity) = attachment.attached_entity(entities) {
                 attachment.update_while_attached(
                     &carrier_entity.sprite,
@@ -33,15 +40,49 @@ pub fn attachment_system(entities: &mut EntityMap, level: &Level, time: &GameTim
                     sprite,
                     &mut entity.physics,
                     time,
+                    &ctx.config,
                 );
             } else if attachment.should_attach {
-                attachment.maybe_attach_to_entity(entities, sprite, &mut entity.physics, level);
+                attachment.maybe_attach_to_entity(
+                    entities,
+                    sprite,
+                    &mut entity.physics,
+                    level,
+                    ctx,
+                );
             }
+            push_attachment_events(id, was_attached_to, attachment, events);
         },
     );
 }
 
-#[derive(Default, Copy, Clone)]
+/// Add events for any change in what the passenger with the given id is attached
+/// to, given what it used to be attached to.
+pub fn push_attachment_events(
+    passenger_id: u64,
+    was_attached_to: Option<u64>,
+    attachment: &AttachmentComponent,
+    events: &mut Vec<GameEvent>,
+) {
+    let attached_to = attachment.attached_to_entity_id();
+    if attached_to == was_attached_to {
+        return;
+    }
+    if let Some(carrier_id) = was_attached_to {
+        events.push(GameEvent::Detached {
+            passenger_id,
+            carrier_id,
+        });
+    }
+    if let Some(carrier_id) = attached_to {
+        events.push(GameEvent::Attached {
+            passenger_id,
+            carrier_id,
+        });
+    }
+}
+
+#[derive(Default, Copy, Clone, Serialize, Deserialize)]
 pub struct AttachmentComponent {
     attached_to_entity_id: Option<u64>,
     detached_from_entity_id: Option<u64>,
@@ -49,7 +90,7 @@ pub struct AttachmentComponent {
     pub should_attach: bool,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct AttachableComponent();
 
 impl AttachmentComponent {
@@ -59,6 +100,7 @@ impl AttachmentComponent {
         passenger_sprite: &SpriteComponent,
         passenger_physics: &mut PhysicsComponent,
         level: &Level,
+        ctx: &GameContext,
     ) {
         let passenger_bbox = &passenger_sprite.bbox();
 
@@ -77,11 +119,14 @@ impl AttachmentComponent {
                 // Check to see if the passenger will fit on the carrier
                 // without running into level geometry.
                 let passenger_bbox = passenger_sprite.bbox();
-                let delta = get_passenger_displacement(&carrier.sprite.bbox(), &passenger_bbox);
+                let delta = get_passenger_displacement(
+                    &carrier.sprite.bbox(),
+                    &passenger_bbox,
+                    &ctx.config,
+                );
                 let projected_passenger_bbox = passenger_bbox.offset(delta);
 
                 if level.is_area_vacant(&projected_passenger_bbox) {
-                    play_sound_effect(game_assets().attach_sound);
                     self.attached_to_entity_id = Some(id);
                     self.num_frames_displaced = 0;
                     passenger_physics.velocity.x = 0.;
@@ -127,6 +172,7 @@ impl AttachmentComponent {
         passenger_sprite: &mut SpriteComponent,
         passenger_physics: &mut PhysicsComponent,
         time: &GameTime,
+        config: &Config,
     ) {
         if passenger_physics.latest_frame.was_displaced {
             // It's possible that the carrier has also just hit something and
@@ -141,10 +187,11 @@ impl AttachmentComponent {
             self.num_frames_displaced = 0;
         }
 
-        let delta = get_passenger_displacement(&carrier_sprite.bbox(), &passenger_sprite.bbox());
+        let delta =
+            get_passenger_displacement(&carrier_sprite.bbox(), &passenger_sprite.bbox(), config);
         let max_delta = carrier_physics.velocity.length()
             * time.time_since_last_frame as f32
-            * config().attach_velocity_coefficient;
+            * config.attach_velocity_coefficient;
 
         passenger_sprite.pos += delta.clamp_length_max(max_delta);
         passenger_sprite.is_facing_left = carrier_sprite.is_facing_left;
@@ -153,8 +200,7 @@ impl AttachmentComponent {
     }
 }
 
-fn get_passenger_displacement(carrier_bbox: &Rect, passenger_bbox: &Rect) -> Vec2 {
-    let config = config();
+fn get_passenger_displacement(carrier_bbox: &Rect, passenger_bbox: &Rect, config: &Config) -> Vec2 {
     let y_diff =
         carrier_bbox.bottom() - config.sprite_scale * CARRY_Y_OFFSET - passenger_bbox.top();
     let x_diff = carrier_bbox.left() - passenger_bbox.left();
@@ -6,6 +6,12 @@ use macroquad::{
     prelude::FileError,
 };
 
+use crate::{
+    events::{GameEvent, PickupType},
+    level::EntityKind,
+    world::World,
+};
+
 pub type SoundEffect = Option<Sound>;
 
 pub async fn load_sound_effect(path: &'static str) -> Result<SoundEffect, FileError> {
@@ -38,3 +44,45 @@ pub fn play_sound_effect_at_volume(sound_effect: SoundEffect, volume: f32) {
 pub fn play_sound_effect(sound_effect: SoundEffect) {
     play_sound_effect_at_volume(sound_effect, 0.25);
 }
+
+/// Play the sound effects for the given events, which just happened in the
+/// given world.
+pub fn play_sound_effects_for_events(events: &[GameEvent], world: &World) {
+    let assets = &world.ctx().assets;
+    for event in events {
+        match event {
+            GameEvent::PickupCollected { kind, .. } => match kind {
+                PickupType::Spear => play_sound_effect(assets.spear.pickup_sound),
+                PickupType::Gem => play_sound_effect(assets.gem.pickup_sound),
+            },
+            GameEvent::SwitchToggled {
+                iid,
+                level,
+                is_first_time_on,
+                started_route,
+                ..
+            } => {
+                // See if the switch's corresponding entity in the level data wants
+                // us to play anything.
+                let trigger = world.level(level).and_then(|level| {
+                    match &level.entities.get(&(*iid)?)?.kind {
+                        EntityKind::Trigger(args) => args.play_sound_effect,
+                        _ => None,
+                    }
+                });
+                if let Some((sound, volume)) = trigger {
+                    if *is_first_time_on {
+                        play_sound_effect_at_volume(sound, volume);
+                    }
+                }
+                if *started_route {
+                    play_sound_effect(assets.switch_sound);
+                }
+            }
+            GameEvent::PlayerJumped { .. } => play_sound_effect(assets.huntress.jump_sound),
+            GameEvent::MushroomRezzed { .. } => play_sound_effect(assets.mushroom.rez_sound),
+            GameEvent::Attached { .. } => play_sound_effect(assets.attach_sound),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,439 @@
+//! A tool for inspecting and converting recordings made by macroquad-fun.
+
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use argh::FromArgs;
+use macroquad_fun::{
+    input::Buttons,
+    recorder::{RecordedChecksum, RecordedFrame, Recording, RecordingMetadata},
+};
+use serde_derive::{Deserialize, Serialize};
+
+/// The fixed FPS to assume for recordings that don't include metadata.
+const DEFAULT_FIXED_FPS: u64 = 60;
+
+#[derive(FromArgs)]
+/// Inspect and convert macroquad-fun recordings. Recordings can be in the
+/// game's binary format, JSON (.json), or CSV (.csv).
+struct Args {
+    #[argh(subcommand)]
+    command: Command,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Dump(DumpArgs),
+    Stats(StatsArgs),
+    Convert(ConvertArgs),
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "dump")]
+/// print the buttons held down on each frame of a recording
+struct DumpArgs {
+    #[argh(positional)]
+    /// recording filename
+    filename: String,
+
+    #[argh(switch)]
+    /// only print frames where the buttons changed
+    changes_only: bool,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "stats")]
+/// print summary statistics about a recording
+struct StatsArgs {
+    #[argh(positional)]
+    /// recording filename
+    filename: String,
+
+    #[argh(option, default = "5.0")]
+    /// minimum number of seconds without any buttons held down for
+    /// a stretch of time to be reported as idle
+    idle_seconds: f64,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "convert")]
+/// convert a recording between formats, based on the filename extensions
+struct ConvertArgs {
+    #[argh(positional)]
+    /// filename to convert from
+    input: String,
+
+    #[argh(positional)]
+    /// filename to convert to
+    output: String,
+}
+
+/// A human-readable, hand-editable version of a recording.
+#[derive(Serialize, Deserialize)]
+struct JsonRecording {
+    metadata: Option<RecordingMetadata>,
+    frames: Vec<JsonFrame>,
+    #[serde(default)]
+    checksums: Vec<RecordedChecksum>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonFrame {
+    frame_number: u64,
+    buttons: Vec<String>,
+}
+
+enum Format {
+    Binary,
+    Json,
+    Csv,
+}
+
+impl Format {
+    fn from_filename(filename: &str) -> Self {
+        match Path::new(filename).extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Format::Json,
+            Some("csv") => Format::Csv,
+            _ => Format::Binary,
+        }
+    }
+}
+
+fn parse_button_names<'a, T: IntoIterator<Item = &'a str>>(names: T) -> Result<Buttons> {
+    let mut buttons = Buttons::empty();
+    for name in names {
+        buttons |= Buttons::from_name(name).ok_or_else(|| anyhow!("Invalid button '{}'", name))?;
+    }
+    Ok(buttons)
+}
+
+fn recording_to_json(recording: &Recording) -> Result<String> {
+    let json_recording = JsonRecording {
+        metadata: recording.metadata.clone(),
+        frames: recording
+            .frames
+            .iter()
+            .map(|frame| JsonFrame {
+                frame_number: frame.frame_number,
+                buttons: frame
+                    .buttons
+                    .to_names()
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+            })
+            .collect(),
+        checksums: recording.checksums.clone(),
+    };
+    Ok(serde_json::to_string_pretty(&json_recording)?)
+}
+
+fn recording_from_json(json: &str) -> Result<Recording> {
+    let json_recording: JsonRecording = serde_json::from_str(json)?;
+    let mut frames = Vec::with_capacity(json_recording.frames.len());
+    for frame in json_recording.frames {
+        frames.push(RecordedFrame {
+            frame_number: frame.frame_number,
+            buttons: parse_button_names(frame.buttons.iter().map(String::as_str))?,
+        });
+    }
+    // JSON doesn't tell us which line each frame came from, so rather than
+    // making people hunt for an out-of-order frame, just put them in order.
+    frames.sort_by_key(|frame| frame.frame_number);
+    for pair in frames.windows(2) {
+        if pair[0].frame_number == pair[1].frame_number {
+            return Err(anyhow!(
+                "Frame {} appears more than once in JSON",
+                pair[0].frame_number
+            ));
+        }
+    }
+    Ok(Recording {
+        metadata: json_recording.metadata,
+        frames,
+        checksums: json_recording.checksums,
+    })
+}
+
+fn recording_to_csv(recording: &Recording) -> String {
+    let mut csv = String::from("frame_number,buttons\n");
+    for frame in recording.frames.iter() {
+        csv.push_str(&format!(
+            "{},{}\n",
+            frame.frame_number,
+            frame.buttons.to_names().join(" ")
+        ));
+    }
+    csv
+}
+
+fn recording_from_csv(csv: &str) -> Result<Recording> {
+    let mut frames: Vec<RecordedFrame> = vec![];
+    for (i, line) in csv.lines().enumerate().skip(1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some((frame_number, buttons)) = line.split_once(',') else {
+            return Err(anyhow!("Line {} of CSV has no comma", i + 1));
+        };
+        let frame_number: u64 = frame_number.trim().parse()?;
+        if let Some(prev_frame) = frames.last() {
+            if frame_number <= prev_frame.frame_number {
+                return Err(anyhow!(
+                    "Line {} of CSV has frame {}, which isn't after frame {} on the line before it",
+                    i + 1,
+                    frame_number,
+                    prev_frame.frame_number
+                ));
+            }
+        }
+        frames.push(RecordedFrame {
+            frame_number,
+            buttons: parse_button_names(buttons.split_whitespace())?,
+        });
+    }
+    Ok(Recording {
+        metadata: None,
+        frames,
+        checksums: vec![],
+    })
+}
+
+fn load_recording(filename: &str) -> Result<Recording> {
+    match Format::from_filename(filename) {
+        Format::Binary => Recording::parse(&std::fs::read(filename)?),
+        Format::Json => recording_from_json(&std::fs::read_to_string(filename)?),
+        Format::Csv => recording_from_csv(&std::fs::read_to_string(filename)?),
+    }
+}
+
+fn save_recording(recording: &Recording, filename: &str) -> Result<()> {
+    match Format::from_filename(filename) {
+        Format::Binary => std::fs::write(filename, recording.to_bytes())?,
+        Format::Json => std::fs::write(filename, recording_to_json(recording)?)?,
+        Format::Csv => {
+            if recording.metadata.is_some() || !recording.checksums.is_empty() {
+                println!("WARNING: CSV files can't store metadata or checksums, so they will be omitted.");
+            }
+            std::fs::write(filename, recording_to_csv(recording))?
+        }
+    }
+    Ok(())
+}
+
+fn fixed_fps(recording: &Recording) -> u64 {
+    recording
+        .metadata
+        .as_ref()
+        .map(|metadata| metadata.fixed_fps)
+        .unwrap_or(DEFAULT_FIXED_FPS)
+}
+
+fn print_metadata(recording: &Recording) {
+    if let Some(metadata) = &recording.metadata {
+        println!("Game version: {}", metadata.game_version);
+        println!("Start position: {}", metadata.start_position);
+        println!("Fixed FPS: {}", metadata.fixed_fps);
+        println!("Config digest: {:016x}", metadata.config_digest);
+        println!("World digest: {:016x}", metadata.world_digest);
+    } else {
+        println!(
+            "Recording has no metadata, assuming {} fixed FPS.",
+            DEFAULT_FIXED_FPS
+        );
+    }
+}
+
+fn dump(args: &DumpArgs) -> Result<()> {
+    let recording = load_recording(&args.filename)?;
+    print_metadata(&recording);
+    let mut checksums = recording.checksums.iter().peekable();
+    let mut prev_buttons: Option<Buttons> = None;
+    for (frame_number, buttons) in recording.iter_buttons().enumerate() {
+        let frame_number = frame_number as u64;
+        if !args.changes_only || prev_buttons != Some(buttons) {
+            println!("{:>8} {}", frame_number, buttons.to_names().join(" "));
+        }
+        if let Some(checksum) = checksums.next_if(|c| c.frame_number == frame_number) {
+            println!("{:>8} checksum {:016x}", frame_number, checksum.checksum);
+        }
+        prev_buttons = Some(buttons);
+    }
+    Ok(())
+}
+
+/// Summary statistics about a recording.
+#[derive(Debug, PartialEq)]
+struct RecordingStats {
+    num_frames: u64,
+    /// How many times each button was pressed, in the same order as `Buttons::all_named()`.
+    presses: Vec<u64>,
+    /// The first frame and length, in frames, of every stretch of time in which no
+    /// buttons were held down for at least the idle threshold.
+    idle_stretches: Vec<(u64, u64)>,
+}
+
+impl RecordingStats {
+    fn new(recording: &Recording, idle_threshold_frames: u64) -> Self {
+        let mut stats = RecordingStats {
+            num_frames: 0,
+            presses: vec![0; Buttons::all_named().count()],
+            idle_stretches: vec![],
+        };
+        let mut prev_buttons = Buttons::empty();
+        let mut idle_start: Option<u64> = None;
+        let mut idle_stretches = vec![];
+        let mut end_idle_stretch = |start: u64, end: u64| {
+            if end - start >= idle_threshold_frames {
+                idle_stretches.push((start, end - start));
+            }
+        };
+        for (frame_number, buttons) in recording.iter_buttons().enumerate() {
+            let frame_number = frame_number as u64;
+            for (i, (button, _)) in Buttons::all_named().enumerate() {
+                if buttons.is_down(button) && !prev_buttons.is_down(button) {
+                    stats.presses[i] += 1;
+                }
+            }
+            if buttons.is_empty() {
+                idle_start.get_or_insert(frame_number);
+            } else if let Some(start) = idle_start.take() {
+                end_idle_stretch(start, frame_number);
+            }
+            prev_buttons = buttons;
+            stats.num_frames = frame_number + 1;
+        }
+        if let Some(start) = idle_start {
+            end_idle_stretch(start, stats.num_frames);
+        }
+        stats.idle_stretches = idle_stretches;
+        stats
+    }
+}
+
+fn stats(args: &StatsArgs) -> Result<()> {
+    let recording = load_recording(&args.filename)?;
+    print_metadata(&recording);
+    let fps = fixed_fps(&recording) as f64;
+    let stats = RecordingStats::new(&recording, (args.idle_seconds * fps).ceil() as u64);
+    println!(
+        "Duration: {} frames ({:.2} seconds)",
+        stats.num_frames,
+        stats.num_frames as f64 / fps
+    );
+    println!("Checksums: {}", recording.checksums.len());
+    for ((_, name), presses) in Buttons::all_named().zip(stats.presses.iter()) {
+        println!("{} presses: {}", name, presses);
+    }
+    let total_idle_frames: u64 = stats.idle_stretches.iter().map(|(_, len)| len).sum();
+    println!(
+        "Idle stretches of at least {:.2} seconds: {} ({:.2} seconds total)",
+        args.idle_seconds,
+        stats.idle_stretches.len(),
+        total_idle_frames as f64 / fps
+    );
+    for (start, len) in stats.idle_stretches.iter() {
+        println!(
+            "  Frame {} ({:.2}s): idle for {:.2} seconds",
+            start,
+            *start as f64 / fps,
+            *len as f64 / fps
+        );
+    }
+    Ok(())
+}
+
+fn convert(args: &ConvertArgs) -> Result<()> {
+    let recording = load_recording(&args.input)?;
+    save_recording(&recording, &args.output)?;
+    println!("Wrote {}.", args.output);
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let args: Args = argh::from_env();
+    match &args.command {
+        Command::Dump(args) => dump(args),
+        Command::Stats(args) => stats(args),
+        Command::Convert(args) => convert(args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad_fun::{
+        input::Buttons,
+        recorder::{RecordedFrame, Recording},
+    };
+
+    use super::{recording_from_csv, recording_from_json, recording_to_csv, RecordingStats};
+
+    fn frame(frame_number: u64, buttons: Buttons) -> RecordedFrame {
+        RecordedFrame {
+            frame_number,
+            buttons,
+        }
+    }
+
+    #[test]
+    fn test_stats_work() {
+        let recording = Recording {
+            metadata: None,
+            frames: vec![
+                frame(0, Buttons::RIGHT),
+                frame(2, Buttons::RIGHT | Buttons::JUMP),
+                frame(3, Buttons::empty()),
+                frame(8, Buttons::JUMP),
+                frame(9, Buttons::empty()),
+                frame(10, Buttons::empty()),
+            ],
+            checksums: vec![],
+        };
+        assert_eq!(
+            RecordingStats::new(&recording, 2),
+            RecordingStats {
+                num_frames: 11,
+                presses: vec![0, 1, 2],
+                idle_stretches: vec![(3, 5), (9, 2)],
+            }
+        );
+    }
+
+    #[test]
+    fn test_csv_round_trips() {
+        let recording = Recording {
+            metadata: None,
+            frames: vec![
+                frame(0, Buttons::empty()),
+                frame(5, Buttons::LEFT | Buttons::JUMP),
+            ],
+            checksums: vec![],
+        };
+        let csv = recording_to_csv(&recording);
+        assert_eq!(csv, "frame_number,buttons\n0,\n5,LEFT JUMP\n");
+        assert_eq!(recording_from_csv(&csv).unwrap(), recording);
+    }
+
+    #[test]
+    fn test_out_of_order_csv_frames_are_rejected() {
+        let csv = "frame_number,buttons\n0,\n5,JUMP\n5,\n";
+        let err = recording_from_csv(csv).unwrap_err();
+        assert!(err.to_string().starts_with("Line 4 of CSV"), "{}", err);
+    }
+
+    #[test]
+    fn test_json_frames_are_sorted() {
+        let json = r#"{"metadata": null, "checksums": [], "frames": [
+            {"frame_number": 5, "buttons": ["JUMP"]},
+            {"frame_number": 0, "buttons": []}
+        ]}"#;
+        let frame_numbers: Vec<u64> = recording_from_json(json)
+            .unwrap()
+            .frames
+            .iter()
+            .map(|frame| frame.frame_number)
+            .collect();
+        assert_eq!(frame_numbers, vec![0, 5]);
+    }
+}
@@ -1,20 +1,23 @@
-use macroquad::{
-    prelude::{pop_camera_state, push_camera_state, set_camera, Camera2D, Rect, Vec2, BLUE},
-    window::{screen_height, screen_width},
-};
+use macroquad::prelude::{pop_camera_state, push_camera_state, set_camera, Camera2D, Rect, Vec2, BLUE};
 
-use crate::{drawing::draw_crosshair, entity::Entity, level::Level, math_util::floor_rect};
+use crate::{
+    config::Config, drawing::draw_crosshair, entity::Entity, level::Level,
+    math_util::floor_rect, serde_util::RectDef,
+};
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 pub struct Camera {
+    #[serde(with = "RectDef")]
     current_rect: Rect,
     target: Vec2,
 }
 
 impl Camera {
-    pub fn new() -> Self {
+    pub fn new(config: &Config) -> Self {
+        // Note that we're using the configured screen size rather than querying
+        // the actual screen here, since we might be running headless.
         Camera {
-            current_rect: Rect::new(0., 0., screen_width(), screen_height()),
+            current_rect: Rect::new(0., 0., config.screen_width, config.screen_height),
             target: Default::default(),
         }
     }
@@ -0,0 +1,47 @@
+use macroquad::prelude::Rect;
+
+use crate::{
+    entity::{Entity, EntityMap},
+    physics::PhysicsComponent,
+    sprite_component::{Renderer, SpriteComponent},
+};
+
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
+pub struct CheckpointComponent {
+    is_touching_player: bool,
+}
+
+pub fn create_checkpoint(rect: Rect) -> Entity {
+    Entity {
+        sprite: SpriteComponent {
+            renderer: Renderer::Invisible,
+            ..Default::default()
+        }
+        .with_pos_and_size(&rect),
+        physics: PhysicsComponent {
+            defies_gravity: true,
+            ..Default::default()
+        },
+        checkpoint: Some(CheckpointComponent::default()),
+        ..Default::default()
+    }
+}
+
+/// Returns whether the main player started touching a checkpoint this frame,
+/// in which case the game should remember the current state so the player can
+/// respawn from it.
+pub fn checkpoint_system(entities: &mut EntityMap) -> bool {
+    let player_bbox = entities.main_player().sprite.bbox();
+    let mut reached_checkpoint = false;
+    for (_id, entity) in entities.iter_mut() {
+        let Some(checkpoint) = entity.checkpoint.as_mut() else {
+            continue;
+        };
+        let was_touching_player = checkpoint.is_touching_player;
+        checkpoint.is_touching_player = entity.sprite.bbox().overlaps(&player_bbox);
+        if checkpoint.is_touching_player && !was_touching_player {
+            reached_checkpoint = true;
+        }
+    }
+    reached_checkpoint
+}
@@ -0,0 +1,186 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{entity::EntityMap, life_transfer::LifeTransfer};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A 64-bit FNV-1a hasher.
+///
+/// We use this instead of the standard library's `DefaultHasher` because its
+/// algorithm is guaranteed never to change, which matters since checksums are
+/// persisted in recordings that may be played back by future builds.
+struct ChecksumHasher(u64);
+
+impl ChecksumHasher {
+    fn new() -> Self {
+        ChecksumHasher(FNV_OFFSET_BASIS)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    fn write_f32(&mut self, value: f32) {
+        self.write_bytes(&value.to_bits().to_le_bytes());
+    }
+
+    fn write_bool(&mut self, value: bool) {
+        self.write_bytes(&[value as u8]);
+    }
+
+    /// Write a reference to the given entity, in a way that doesn't depend on the
+    /// order in which level entities were assigned ids.
+    fn write_entity_ref(&mut self, entities: &EntityMap, id: u64) {
+        if let Some(iid) = entities.get(id).and_then(|entity| entity.iid) {
+            self.write_bool(true);
+            self.write_bytes(iid.as_bytes());
+        } else {
+            self.write_bool(false);
+            self.write_u64(id);
+        }
+    }
+}
+
+/// Compute a digest of the given content, e.g. the contents of a file.
+pub fn digest(content: &[u8]) -> u64 {
+    let mut hasher = ChecksumHasher::new();
+    hasher.write_bytes(content);
+    hasher.0
+}
+
+/// Compute a checksum of the simulation-relevant state of the given entities.
+///
+/// This doesn't cover everything, but it includes enough (positions, velocities,
+/// and the most important bits of component state) that any divergence in the
+/// simulation should show up in it within a few frames.
+pub fn checksum_entities(level_identifier: &str, entities: &EntityMap) -> u64 {
+    let mut hasher = ChecksumHasher::new();
+    hasher.write_bytes(level_identifier.as_bytes());
+
+    // Identify level entities by their iids rather than their ids, so that checksums
+    // don't depend on how ids are allocated (which has changed over time). Other
+    // entities (like the main player) are identified by their ids.
+    let mut ids: Vec<u64> = entities.ids().collect();
+    ids.sort_unstable_by_key(|&id| (entities.get(id).unwrap().iid, id));
+
+    for id in ids {
+        let entity = entities.get(id).unwrap();
+        hasher.write_entity_ref(entities, id);
+        hasher.write_f32(entity.sprite.pos.x);
+        hasher.write_f32(entity.sprite.pos.y);
+        hasher.write_bool(entity.sprite.is_facing_left);
+        hasher.write_u64(entity.sprite.current_frame_number as u64);
+        hasher.write_f32(entity.physics.velocity.x);
+        hasher.write_f32(entity.physics.velocity.y);
+        hasher.write_bool(entity.physics.defies_gravity);
+        hasher.write_bool(entity.mushroom.is_some());
+        hasher.write_bool(entity.dynamic_collider.is_some());
+        hasher.write_bool(entity.pickup.is_some());
+        if let Some(player) = &entity.player {
+            hasher.write_bool(player.inventory.has_spear);
+        }
+        if let Some(attachment) = &entity.attachment {
+            hasher.write_bool(attachment.is_attached());
+            if let Some(attached_id) = attachment.attached_to_entity_id() {
+                hasher.write_entity_ref(entities, attached_id);
+            }
+        }
+        if let Some(switch) = &entity.switch {
+            hasher.write_bool(switch.is_switched_on);
+        }
+        if let Some(route) = &entity.route {
+            hasher.write_bool(route.is_moving);
+            hasher.write_bool(route.is_moving_towards_start);
+        }
+        match entity.life_transfer {
+            Some(LifeTransfer::Giving(amount)) => {
+                hasher.write_bytes(&[1]);
+                hasher.write_f32(amount);
+            }
+            Some(LifeTransfer::Receiving(amount)) => {
+                hasher.write_bytes(&[2]);
+                hasher.write_f32(amount);
+            }
+            None => hasher.write_bytes(&[0]),
+        }
+    }
+
+    hasher.0
+}
+
+/// Information about the first frame at which a replay diverged from the
+/// original recording.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Desync {
+    pub frame_number: u64,
+    pub expected_checksum: u64,
+    pub actual_checksum: u64,
+}
+
+/// Allows checksums of the game state to be exchanged between the game loop
+/// and whatever is recording or playing back its input.
+///
+/// The recorder (or player) requests a checksum for a particular frame, and
+/// the game loop provides it once it has finished simulating that frame.
+#[derive(Default)]
+pub struct ChecksumChannel {
+    /// How often, in frames, to record checksums. If zero, no checksums will
+    /// be recorded. This isn't used for playback, which verifies checksums
+    /// whenever the recording contains them.
+    pub interval: u64,
+    requested_frame: Option<u64>,
+    provided: Option<(u64, u64)>,
+    first_desync: Option<Desync>,
+}
+
+pub type SharedChecksumChannel = Rc<RefCell<ChecksumChannel>>;
+
+impl ChecksumChannel {
+    pub fn new_shared(interval: u64) -> SharedChecksumChannel {
+        Rc::new(RefCell::new(ChecksumChannel {
+            interval,
+            ..Default::default()
+        }))
+    }
+
+    pub fn request(&mut self, frame_number: u64) {
+        self.requested_frame = Some(frame_number);
+    }
+
+    /// If a checksum was requested, call the given function to compute it. This
+    /// should be called by the game loop after simulating each frame.
+    pub fn provide_if_requested<F: FnOnce() -> u64>(&mut self, compute_checksum: F) {
+        if let Some(frame_number) = self.requested_frame.take() {
+            self.provided = Some((frame_number, compute_checksum()));
+        }
+    }
+
+    /// Returns the frame number and checksum the game loop provided, if any.
+    pub fn take_provided(&mut self) -> Option<(u64, u64)> {
+        self.provided.take()
+    }
+
+    /// Record a desync, if it's the first one we've encountered. Returns whether
+    /// it was the first one.
+    pub fn report_desync(&mut self, desync: Desync) -> bool {
+        if self.first_desync.is_none() {
+            self.first_desync = Some(desync);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn first_desync(&self) -> Option<Desync> {
+        self.first_desync
+    }
+}
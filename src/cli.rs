@@ -1,5 +1,7 @@
 use argh::FromArgs;
 
+use crate::recorder::DEFAULT_CHECKSUM_INTERVAL;
+
 const DEFAULT_START_POSITION: &str = "default";
 
 #[derive(argh::FromArgs)]
@@ -17,6 +19,10 @@ pub struct Cli {
     /// filename to play back recording from
     pub playback: Option<String>,
 
+    #[argh(option, default = "DEFAULT_CHECKSUM_INTERVAL")]
+    /// how often, in frames, to record checksums of the game state (0 to disable)
+    pub checksum_interval: u64,
+
     #[argh(switch)]
     /// exit at end of playback (only used with --playback).
     pub stop_at_end: bool,
@@ -36,12 +42,22 @@ pub struct Cli {
     #[argh(switch)]
     /// enable fullscreen mode
     pub fullscreen: bool,
+
+    #[argh(switch)]
+    /// run the simulation as fast as possible without a window (requires --playback)
+    pub headless: bool,
+}
+
+impl Default for Cli {
+    fn default() -> Self {
+        Cli::from_args(&["macroquad-fun"], &[]).unwrap()
+    }
 }
 
 impl Cli {
     pub fn get_for_platform() -> Self {
         if cfg!(target_arch = "wasm32") {
-            Cli::from_args(&["macroquad-fun"], &[]).unwrap()
+            Cli::default()
         } else {
             argh::from_env()
         }
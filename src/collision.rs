@@ -1,7 +1,7 @@
 use bitflags::bitflags;
 use macroquad::prelude::*;
 
-use crate::math_util::are_opposites;
+use crate::{math_util::are_opposites, serde_util::RectDef};
 
 #[derive(Debug)]
 pub struct Collision {
@@ -10,6 +10,7 @@ pub struct Collision {
 }
 
 bitflags! {
+    #[derive(Serialize, Deserialize)]
     pub struct CollisionFlags: u32 {
         const ENVIRONMENT = 0b00000001;
         const PLAYER_ONLY = 0b00000010;
@@ -22,9 +23,11 @@ impl Default for CollisionFlags {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
 pub struct Collider {
+    #[serde(with = "RectDef")]
     pub rect: Rect,
+    #[serde(with = "RectDef")]
     pub prev_rect: Rect,
     pub flags: CollisionFlags,
     pub entity_id: Option<u64>,
@@ -33,6 +36,38 @@ pub struct Collider {
     pub enable_right: bool,
     pub enable_left: bool,
     pub velocity: Vec2,
+    #[serde(default)]
+    pub shape: ColliderShape,
+}
+
+/// The shape of a collider's top side.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ColliderShape {
+    /// The top side is just the top edge of the collider's rect.
+    #[default]
+    Flat,
+
+    /// The top side slopes from one side of the collider's rect to the other.
+    /// `left` and `right` are how far above the bottom of the rect the slope is at
+    /// its left and right edges, as fractions of the rect's height.
+    ///
+    /// Only the higher end of the slope has a wall, and the collider's
+    /// `enable_left` and `enable_right` should reflect that.
+    Slope { left: f32, right: f32 },
+}
+
+impl ColliderShape {
+    /// Returns the y-coordinate of the top side of a collider with this shape
+    /// occupying the given rect, at the given x-coordinate.
+    pub fn top_at(&self, rect: &Rect, x: f32) -> f32 {
+        match self {
+            ColliderShape::Flat => rect.top(),
+            ColliderShape::Slope { left, right } => {
+                let t = ((x - rect.left()) / rect.w).clamp(0., 1.);
+                rect.bottom() - rect.h * (left + (right - left) * t)
+            }
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -55,12 +90,14 @@ pub const EXTRA_DISPLACEMENT: f32 = 0.001;
 impl Collider {
     pub fn draw_debug_rect(&self, color: Color) {
         let thickness = 2.;
+        let top_left = self.shape.top_at(&self.rect, self.rect.left());
+        let top_right = self.shape.top_at(&self.rect, self.rect.right());
         if self.enable_top {
             draw_line(
                 self.rect.left(),
-                self.rect.top(),
+                top_left,
                 self.rect.right(),
-                self.rect.top(),
+                top_right,
                 thickness,
                 color,
             );
@@ -78,7 +115,7 @@ impl Collider {
         if self.enable_left {
             draw_line(
                 self.rect.left(),
-                self.rect.top(),
+                top_left,
                 self.rect.left(),
                 self.rect.bottom(),
                 thickness,
@@ -88,7 +125,7 @@ impl Collider {
         if self.enable_right {
             draw_line(
                 self.rect.right(),
-                self.rect.top(),
+                top_right,
                 self.rect.right(),
                 self.rect.bottom(),
                 thickness,
@@ -104,6 +141,15 @@ pub fn process_collision(
     actor_bbox: &Rect,
     vertical_collision_leeway: f32,
 ) -> Option<Collision> {
+    if let ColliderShape::Slope { .. } = collider.shape {
+        return process_slope_collision(
+            collider,
+            actor_prev_bbox,
+            actor_bbox,
+            vertical_collision_leeway,
+        );
+    }
+
     let collider_rect = collider.rect;
 
     if let Some(intersection) = collider_rect.intersect(*actor_bbox) {
@@ -183,6 +229,102 @@ pub fn process_collision(
     None
 }
 
+/// Like `process_collision()`, but for colliders with a sloped top side.
+///
+/// Actors stand on the slope at whatever height it has beneath their horizontal
+/// center, so they can walk onto it from its lower end without bumping into
+/// anything.
+fn process_slope_collision(
+    collider: &Collider,
+    actor_prev_bbox: &Rect,
+    actor_bbox: &Rect,
+    vertical_collision_leeway: f32,
+) -> Option<Collision> {
+    let collider_rect = collider.rect;
+    let prev_rect = collider.prev_rect;
+    let intersection = collider_rect.intersect(*actor_bbox)?;
+    let is_actor_not_moving_up = actor_prev_bbox.y <= actor_bbox.y;
+
+    if collider.enable_top {
+        let top = collider.shape.top_at(&collider_rect, actor_bbox.center().x);
+        let prev_top = collider
+            .shape
+            .top_at(&prev_rect, actor_prev_bbox.center().x);
+        let prev_height_above_slope = prev_top - actor_prev_bbox.bottom();
+        // Make sure the actor was above the slope in the last frame, just like
+        // we do for the top side of flat colliders.
+        if prev_height_above_slope >= 0. {
+            let y_diff = actor_bbox.bottom() - top;
+            // If the actor was on the slope in the last frame and isn't on their
+            // way up, keep them on it even if it dropped out from under them
+            // (e.g. because they're walking down it). Otherwise they'd bounce
+            // down it as though they were falling down a flight of stairs.
+            let max_snap = vertical_collision_leeway + (top - prev_top).max(0.);
+            let should_snap_to_slope = is_actor_not_moving_up
+                && prev_height_above_slope <= vertical_collision_leeway
+                && -y_diff <= max_snap
+                // If they're already resting on the slope, leave them be.
+                && -y_diff > EXTRA_DISPLACEMENT * 2.;
+            if y_diff > 0. || should_snap_to_slope {
+                return Some(Collision {
+                    side: Side::Top,
+                    displacement: Vec2::new(0., -y_diff - EXTRA_DISPLACEMENT),
+                });
+            }
+        }
+    }
+
+    if collider.enable_bottom
+        && intersection.bottom() >= collider_rect.bottom()
+        && actor_prev_bbox.top() >= prev_rect.bottom()
+    {
+        let y_diff = collider_rect.bottom() - actor_bbox.top();
+        return Some(Collision {
+            side: Side::Bottom,
+            displacement: Vec2::new(0., y_diff + EXTRA_DISPLACEMENT),
+        });
+    }
+
+    // The wall at the slope's higher end can only be hit by an actor who was
+    // entirely on the other side of it in the last frame; otherwise they're
+    // walking up the slope towards it from inside our rect.
+    let (wall_top, collision) =
+        if collider.enable_left && actor_prev_bbox.right() <= prev_rect.left() {
+            let x_diff = actor_bbox.right() - collider_rect.left();
+            (
+                collider.shape.top_at(&collider_rect, collider_rect.left()),
+                Collision {
+                    side: Side::Left,
+                    displacement: Vec2::new(-x_diff - EXTRA_DISPLACEMENT, 0.),
+                },
+            )
+        } else if collider.enable_right && actor_prev_bbox.left() >= prev_rect.right() {
+            let x_diff = collider_rect.right() - actor_bbox.left();
+            (
+                collider.shape.top_at(&collider_rect, collider_rect.right()),
+                Collision {
+                    side: Side::Right,
+                    displacement: Vec2::new(x_diff + EXTRA_DISPLACEMENT, 0.),
+                },
+            )
+        } else {
+            return None;
+        };
+    let y_diff = actor_bbox.bottom() - wall_top;
+    if y_diff <= 0. {
+        return None;
+    }
+    if collider.enable_top && y_diff <= vertical_collision_leeway && is_actor_not_moving_up {
+        // Just like with flat colliders, if the actor is barely hitting the
+        // wall, put them on top of it instead.
+        return Some(Collision {
+            side: Side::Top,
+            displacement: Vec2::new(0., -y_diff - EXTRA_DISPLACEMENT),
+        });
+    }
+    Some(collision)
+}
+
 pub struct CollisionResolutionResult {
     pub displacements: u32,
     pub aborted: bool,
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use macroquad::prelude::*;
 
-use crate::cli::Cli;
+use crate::{asset_loader::AssetLoader, checksum::digest, cli::Cli, input::KeyBindings};
 
 extern crate serde_derive;
 
@@ -32,12 +32,23 @@ pub struct Config {
     pub vertical_collision_leeway: f32,
     pub life_transfer_rate: f32,
     pub coyote_time_ms: f64,
+    pub jump_buffer_ms: f64,
     pub debug_text_size: f32,
+    #[serde(default)]
+    pub key_bindings: KeyBindings,
+
+    /// A digest of the config file's contents, used to identify which
+    /// configuration a recording was made with.
+    #[serde(skip)]
+    pub digest: u64,
 }
 
 pub fn parse_config(config: &str, args: &Cli) -> Result<Config> {
+    let digest = digest(config.as_bytes());
     let mut config: Config = serde_json::from_str(config)?;
 
+    config.digest = digest;
+
     config.run_speed *= config.sprite_scale;
     config.gravity *= config.sprite_scale;
     config.jump_velocity *= config.sprite_scale;
@@ -64,22 +75,6 @@ pub fn parse_config(config: &str, args: &Cli) -> Result<Config> {
     Ok(config)
 }
 
-pub async fn load_config(path: &str, args: &Cli) -> Result<()> {
-    let config = parse_config(&load_string(path).await?, args)?;
-
-    unsafe {
-        CONFIG = Some(config);
-    }
-
-    Ok(())
+pub async fn load_config(path: &str, args: &Cli, loader: &AssetLoader) -> Result<Config> {
+    parse_config(&loader.load_string(path).await?, args)
 }
-
-pub fn config() -> &'static Config {
-    unsafe {
-        CONFIG
-            .as_ref()
-            .expect("load_config() was not called or did not finish")
-    }
-}
-
-static mut CONFIG: Option<Config> = None;
@@ -0,0 +1,42 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use crate::{
+    asset_loader::AssetLoader,
+    cli::Cli,
+    config::{load_config, Config},
+    game_assets::{load_game_assets, GameAssets},
+};
+
+/// Everything the game needs that doesn't change while it's running: its
+/// configuration and its assets.
+///
+/// Nothing here is global, so multiple contexts (e.g. with different configs)
+/// can coexist in the same process.
+pub struct GameContext {
+    pub config: Config,
+    /// Contexts created via [`GameContext::with_config`] share these.
+    pub assets: Rc<GameAssets>,
+}
+
+impl GameContext {
+    /// Load the config at the given path, along with the game assets.
+    pub async fn load(config_path: &str, args: &Cli, loader: AssetLoader) -> Result<Rc<Self>> {
+        let config = load_config(config_path, args, &loader).await?;
+        let assets = load_game_assets(&config, loader).await?;
+        Ok(Rc::new(GameContext {
+            config,
+            assets: Rc::new(assets),
+        }))
+    }
+
+    /// Create a new context that has the given config, but shares this
+    /// context's assets.
+    pub fn with_config(&self, config: Config) -> Rc<Self> {
+        Rc::new(GameContext {
+            config,
+            assets: self.assets.clone(),
+        })
+    }
+}
@@ -2,7 +2,7 @@ use macroquad::prelude::{Rect, BROWN};
 
 use crate::{
     collision::CollisionFlags,
-    config::config,
+    context::GameContext,
     dynamic_collider::{DynamicColliderComponent, RelativeCollider},
     entity::Entity,
     math_util::contract_rect,
@@ -12,13 +12,13 @@ use crate::{
     z_index::ZIndexComponent,
 };
 
-pub fn create_crate(start_rect: Rect) -> Entity {
+pub fn create_crate(start_rect: Rect, ctx: &GameContext) -> Entity {
     let start_point = start_rect.point();
     let relative_bbox = start_rect.offset(-start_point);
     Entity {
         sprite: SpriteComponent {
             pos: start_point,
-            base_relative_bbox: contract_rect(&relative_bbox, config().sprite_scale),
+            base_relative_bbox: contract_rect(&relative_bbox, ctx.config.sprite_scale),
             renderer: Renderer::SolidRectangle(relative_bbox),
             color: Some(BROWN),
             ..Default::default()
@@ -38,7 +38,7 @@ pub fn create_crate(start_rect: Rect) -> Entity {
             enable_right: true,
         })),
         push: Some(PushComponent {
-            pushable_coefficient: config().crate_pushable_coefficient,
+            pushable_coefficient: ctx.config.crate_pushable_coefficient,
             ..Default::default()
         }),
         ..Default::default()
@@ -2,7 +2,7 @@ use anyhow::Result;
 use macroquad::{prelude::YELLOW, text::draw_text};
 use std::fmt::Write;
 
-use crate::{config::config, fps::FpsCounter, level_runtime::LevelRuntime};
+use crate::{fps::FpsCounter, level_runtime::LevelRuntime};
 
 const DEBUG_TEXT_CAPACITY: usize = 3000;
 
@@ -43,7 +43,7 @@ impl DebugMode {
     pub fn draw(&self, runtime: &LevelRuntime) {
         runtime.draw_debug_layer();
 
-        let font_size = config().debug_text_size;
+        let font_size = runtime.ctx().config.debug_text_size;
         let margin = 32.;
         let x = margin;
         let mut y = margin;
@@ -1,14 +1,17 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use macroquad::prelude::{Rect, PURPLE};
 
 use crate::{
-    collision::{Collider, CollisionFlags},
-    entity::{Entity, EntityMap, HeaplessEntityVec},
+    collision::{Collider, ColliderShape, CollisionFlags},
+    entity::{Entity, EntityMap},
+    serde_util::RectDef,
+    spatial_index::SpatialIndex,
 };
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 pub struct RelativeCollider {
+    #[serde(with = "RectDef")]
     pub rect: Rect,
     pub collision_flags: CollisionFlags,
     pub enable_top: bool,
@@ -17,7 +20,7 @@ pub struct RelativeCollider {
     pub enable_left: bool,
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 pub struct DynamicColliderComponent {
     relative_collider: RelativeCollider,
 }
@@ -28,22 +31,23 @@ impl DynamicColliderComponent {
     }
 }
 
-#[derive(Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct DynamicColliderSystem {
     /// Computed values of all the dynamic colliders that currently exist.
     /// This is done partly, for efficiency, but also because it's hard
     /// for our physics system to do nested loops over all entities,
     /// given Rust's borrow checker.
-    colliders: HashMap<u64, Collider>,
+    ///
+    /// These are ordered by entity id, so that collisions are always
+    /// resolved in the same order.
+    colliders: BTreeMap<u64, Collider>,
+    /// An index of the colliders' current rects. This isn't serialized, since
+    /// `run()` rebuilds it.
+    #[serde(skip)]
+    index: SpatialIndex,
 }
 
 impl DynamicColliderSystem {
-    pub fn with_capacity(capacity: usize) -> Self {
-        DynamicColliderSystem {
-            colliders: HashMap::with_capacity(capacity),
-        }
-    }
-
     /// Update the DynamicCollider components based on their sprites' current
     /// positions.
     pub fn run(&mut self, entities: &mut EntityMap) {
@@ -54,15 +58,15 @@ impl DynamicColliderSystem {
         }
 
         // Now remove any stale colliders that no longer exist.
-        let mut colliders_to_remove: HeaplessEntityVec = heapless::Vec::new();
-        colliders_to_remove.extend(
-            self.colliders
-                .keys()
-                .filter(|&id| !entities.contains(*id))
-                .cloned(),
-        );
+        let colliders_to_remove: Vec<u64> = self
+            .colliders
+            .keys()
+            .filter(|&id| !entities.contains(*id))
+            .cloned()
+            .collect();
         for id in colliders_to_remove {
             self.colliders.remove(&id);
+            self.index.remove(id);
         }
     }
 
@@ -88,6 +92,7 @@ impl DynamicColliderSystem {
                 }
             };
             let relative = &dynamic_collider.relative_collider;
+            self.index.update(id, rect);
             self.colliders.insert(
                 id,
                 Collider {
@@ -100,6 +105,7 @@ impl DynamicColliderSystem {
                     enable_bottom: relative.enable_bottom,
                     enable_right: relative.enable_right,
                     enable_left: relative.enable_left,
+                    shape: ColliderShape::Flat,
                 },
             );
         }
@@ -109,8 +115,13 @@ impl DynamicColliderSystem {
         self.update_dynamic_collider_impl(entity_id, entity, false);
     }
 
-    pub fn colliders(&self) -> impl Iterator<Item = &Collider> {
-        self.colliders.values()
+    /// Returns all the dynamic colliders that overlap the given rect, in order
+    /// of their entity ids.
+    pub fn colliders_overlapping(&self, rect: &Rect) -> impl Iterator<Item = Collider> + '_ {
+        self.index
+            .query(rect)
+            .into_iter()
+            .map(|id| self.colliders[&id])
     }
 
     pub fn draw_debug_rects(&self) {
@@ -1,9 +1,10 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::BTreeMap, fmt::Display};
 
 use uuid::Uuid;
 
 use crate::{
     attachment::{AttachableComponent, AttachmentComponent},
+    checkpoint::CheckpointComponent,
     dynamic_collider::DynamicColliderComponent,
     floor_switch::FloorSwitchComponent,
     flying_eye::FlyingEyeComponent,
@@ -23,7 +24,7 @@ use crate::{
     z_index::ZIndexComponent,
 };
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 pub struct Entity {
     pub sprite: SpriteComponent,
     pub physics: PhysicsComponent,
@@ -39,12 +40,15 @@ pub struct Entity {
     pub push: Option<PushComponent>,
     pub switch: Option<SwitchComponent>,
     pub floor_switch: Option<FloorSwitchComponent>,
+    pub checkpoint: Option<CheckpointComponent>,
     pub pickup: Option<PickupComponent>,
     pub steering: Option<SteeringComponent>,
     pub life_transfer: Option<LifeTransferComponent>,
     pub text: Option<TextComponent>,
     pub child: Option<ChildComponent>,
     pub iid: Option<Uuid>,
+    /// This isn't serialized, so it will be `None` for entities in saved games.
+    #[serde(skip)]
     pub name_for_debugging: Option<&'static str>,
 }
 
@@ -62,35 +66,134 @@ impl Display for Entity {
 
 pub const MAIN_PLAYER_ENTITY_ID: u64 = 0;
 
-#[derive(Clone)]
+/// Entity ids pack a slot index into their high 32 bits and the slot's
+/// generation into their low 32 bits. Every time a slot is reused, its
+/// generation goes up, so ids for entities that have since been removed
+/// can be told apart from ids for whatever replaced them.
+///
+/// Because the index is in the high bits, sorting ids numerically puts
+/// them in the same order that `EntityMap` iterates over them.
+fn make_id(index: usize, generation: u32) -> u64 {
+    ((index as u64) << 32) | generation as u64
+}
+
+fn split_id(id: u64) -> (usize, u32) {
+    ((id >> 32) as usize, (id & 0xffff_ffff) as u32)
+}
+
+// Most slots are occupied, so boxing entities wouldn't save any space.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Serialize, Deserialize)]
+enum SlotState {
+    /// Nothing is using this slot, so it's on the free list.
+    Vacant,
+    /// The slot's id has been handed out, but there's no entity in it right
+    /// now (e.g. because the entity is being processed, or is part of a
+    /// level the main player isn't in).
+    Reserved,
+    Occupied(Entity),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Slot {
+    generation: u32,
+    state: SlotState,
+}
+
+/// All the entities in the game, stored in a vector of generational slots.
+///
+/// Iterating over the entities (via `iter()`, `iter_mut()`, `ids()`, or
+/// `filter_and_process_entities()`) always visits them in ascending order of
+/// their ids. Systems that resolve conflicts between entities, e.g. two
+/// pickups overlapping the player in the same frame, rely on this to behave
+/// the same way every time a recording is replayed.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct EntityMap {
-    map: HashMap<u64, Entity>,
-    iid_map: HashMap<Uuid, u64>,
-    next_id: u64,
+    slots: Vec<Slot>,
+    free_list: Vec<usize>,
+    iid_map: BTreeMap<Uuid, u64>,
+    len: usize,
 }
 
 impl EntityMap {
+    /// Reserve a new id, which can be passed to `insert()`. If it isn't going to
+    /// be used, it should be given back with `remove()`.
     pub fn new_id(&mut self) -> u64 {
-        let id = self.next_id;
-        self.next_id += 1;
-        id
+        if let Some(index) = self.free_list.pop() {
+            let slot = &mut self.slots[index];
+            slot.generation += 1;
+            slot.state = SlotState::Reserved;
+            make_id(index, slot.generation)
+        } else {
+            self.slots.push(Slot {
+                generation: 0,
+                state: SlotState::Reserved,
+            });
+            make_id(self.slots.len() - 1, 0)
+        }
+    }
+
+    fn slot(&self, id: u64) -> Option<&Slot> {
+        let (index, generation) = split_id(id);
+        self.slots
+            .get(index)
+            .filter(|slot| slot.generation == generation)
+    }
+
+    fn slot_mut(&mut self, id: u64) -> Option<&mut Slot> {
+        let (index, generation) = split_id(id);
+        self.slots
+            .get_mut(index)
+            .filter(|slot| slot.generation == generation)
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (u64, &Entity)> {
-        self.map.iter().map(|(&id, entity)| (id, entity))
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| match &slot.state {
+                SlotState::Occupied(entity) => Some((make_id(index, slot.generation), entity)),
+                _ => None,
+            })
     }
 
     pub fn iter_mut(&mut self) -> impl Iterator<Item = (u64, &mut Entity)> {
-        self.map.iter_mut().map(|(&id, entity)| (id, entity))
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| match &mut slot.state {
+                SlotState::Occupied(entity) => Some((make_id(index, slot.generation), entity)),
+                _ => None,
+            })
     }
 
     pub fn contains(&self, id: u64) -> bool {
-        self.map.contains_key(&id)
+        self.get(id).is_some()
     }
 
+    /// Returns whether the given id refers to an entity that has been removed,
+    /// as opposed to one that's just temporarily out of the map.
+    pub fn is_stale(&self, id: u64) -> bool {
+        !matches!(
+            self.slot(id),
+            Some(Slot {
+                state: SlotState::Reserved | SlotState::Occupied(..),
+                ..
+            })
+        )
+    }
+
+    /// Put the given entity in the slot for the given id, which must have come
+    /// from `new_id()` (or `remove_all_except_main_player()`) and must not
+    /// already have an entity in it.
     pub fn insert(&mut self, id: u64, entity: Entity) {
-        assert!(!self.map.contains_key(&id), "Entity with id already exists");
-        self.map.insert(id, entity);
+        let slot = self.slot_mut(id).expect("Entity id should not be stale");
+        assert!(
+            matches!(slot.state, SlotState::Reserved),
+            "Entity with id already exists"
+        );
+        slot.state = SlotState::Occupied(entity);
+        self.len += 1;
         if let Some(iid) = entity.iid {
             let result = self.iid_map.insert(iid, id);
             if result.is_some() {
@@ -99,26 +202,51 @@ impl EntityMap {
         }
     }
 
+    /// Take the entity with the given id out of the map, but keep its id
+    /// reserved so it can be put back later with `insert()`.
+    fn take(&mut self, id: u64) -> Option<Entity> {
+        let slot = self.slot_mut(id)?;
+        let SlotState::Occupied(entity) = slot.state else {
+            return None;
+        };
+        slot.state = SlotState::Reserved;
+        self.len -= 1;
+        if let Some(iid) = entity.iid {
+            self.iid_map.remove(&iid);
+        }
+        Some(entity)
+    }
+
+    /// Remove the entity with the given id, making the id stale.
     pub fn remove(&mut self, id: u64) -> Option<Entity> {
-        let result = self.map.remove(&id);
-        if let Some(entity) = result {
-            if let Some(iid) = entity.iid {
-                self.iid_map.remove(&iid);
+        let result = self.take(id);
+        let (index, _generation) = split_id(id);
+        // If the entity was taken out above, its slot is now reserved. If the
+        // slot is already vacant, the id was removed before and its index is
+        // already on the free list.
+        if let Some(slot) = self.slot_mut(id) {
+            if matches!(slot.state, SlotState::Reserved) {
+                slot.state = SlotState::Vacant;
+                self.free_list.push(index);
             }
         }
         result
     }
 
     pub fn capacity(&self) -> usize {
-        self.map.capacity()
+        self.slots.capacity()
     }
 
     pub fn len(&self) -> usize {
-        self.map.len()
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 
-    pub fn ids(&self) -> impl Iterator<Item = &u64> {
-        self.map.keys()
+    pub fn ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.iter().map(|(id, _entity)| id)
     }
 
     pub fn get_id_for_iid(&self, iid: Uuid) -> Option<u64> {
@@ -126,48 +254,66 @@ impl EntityMap {
     }
 
     pub fn get(&self, id: u64) -> Option<&Entity> {
-        self.map.get(&id)
+        match &self.slot(id)?.state {
+            SlotState::Occupied(entity) => Some(entity),
+            _ => None,
+        }
     }
 
     pub fn get_mut(&mut self, id: u64) -> Option<&mut Entity> {
-        self.map.get_mut(&id)
+        match &mut self.slot_mut(id)?.state {
+            SlotState::Occupied(entity) => Some(entity),
+            _ => None,
+        }
     }
 
     pub fn main_player(&self) -> &Entity {
-        &self.map[&MAIN_PLAYER_ENTITY_ID]
+        self.get(MAIN_PLAYER_ENTITY_ID).unwrap()
     }
 
     pub fn main_player_mut(&mut self) -> &mut Entity {
-        self.map.get_mut(&MAIN_PLAYER_ENTITY_ID).unwrap()
+        self.get_mut(MAIN_PLAYER_ENTITY_ID).unwrap()
     }
 
-    pub fn clear_all_except_main_player(&mut self) {
-        self.map.retain(|&key, entity| {
-            if key == MAIN_PLAYER_ENTITY_ID {
-                return true;
-            }
-
-            // Note that this only preserves direct children
-            // of the player, not all descendants!
-            if let Some(child) = entity.child {
-                return child.parent == MAIN_PLAYER_ENTITY_ID;
-            }
+    /// Remove all entities except the main player, returning the ones that
+    /// were removed along with their ids.
+    ///
+    /// The ids stay reserved, so the entities can be put back later with
+    /// `insert()`. Any that won't be put back should be given up with `remove()`.
+    pub fn remove_all_except_main_player(&mut self) -> Vec<(u64, Entity)> {
+        let ids_to_remove: Vec<u64> = self
+            .iter()
+            .filter(|&(id, entity)| {
+                if id == MAIN_PLAYER_ENTITY_ID {
+                    return false;
+                }
 
-            if let Some(iid) = entity.iid {
-                self.iid_map.remove(&iid);
-            }
+                // Note that this only preserves direct children
+                // of the player, not all descendants!
+                if let Some(child) = entity.child {
+                    return child.parent != MAIN_PLAYER_ENTITY_ID;
+                }
 
-            return false;
-        });
+                true
+            })
+            .map(|(id, _entity)| id)
+            .collect();
+        ids_to_remove
+            .into_iter()
+            .map(|id| (id, self.take(id).unwrap()))
+            .collect()
     }
 
     pub fn new_ex(main_player: Entity, capacity: usize) -> Self {
         let mut map = EntityMap {
-            map: HashMap::with_capacity(capacity),
-            iid_map: HashMap::with_capacity(capacity),
-            next_id: 1,
+            slots: Vec::with_capacity(capacity),
+            free_list: vec![],
+            iid_map: BTreeMap::new(),
+            len: 0,
         };
-        map.insert(MAIN_PLAYER_ENTITY_ID, main_player);
+        let id = map.new_id();
+        assert_eq!(id, MAIN_PLAYER_ENTITY_ID);
+        map.insert(id, main_player);
         map
     }
 
@@ -177,16 +323,12 @@ impl EntityMap {
     /// This is useful for situations where we need to be able to mutate an Entity,
     /// but also look at other Entities while mutating it.
     fn with_entity_removed<F: FnOnce(&mut Entity, &mut EntityMap, u64)>(&mut self, id: u64, f: F) {
-        let mut entity = self.remove(id).unwrap();
+        let mut entity = self.take(id).unwrap();
         f(&mut entity, self, id);
         self.insert(id, entity);
     }
 }
 
-pub const ENTITY_MAX: usize = 1000;
-
-pub type HeaplessEntityVec = heapless::Vec<u64, ENTITY_MAX>;
-
 pub fn filter_and_process_entities<
     Filter: Fn(&Entity) -> bool,
     Processor: FnMut(&mut Entity, &mut EntityMap, u64),
@@ -195,17 +337,59 @@ pub fn filter_and_process_entities<
     filter: Filter,
     mut processor: Processor,
 ) {
-    let mut entities_to_process: HeaplessEntityVec = heapless::Vec::new();
-
-    entities_to_process.extend(entities.iter().filter_map(|(id, entity)| {
-        if filter(entity) {
-            Some(id)
-        } else {
-            None
-        }
-    }));
+    let entities_to_process: Vec<u64> = entities
+        .iter()
+        .filter_map(|(id, entity)| if filter(entity) { Some(id) } else { None })
+        .collect();
 
-    for &id in entities_to_process.iter() {
+    for id in entities_to_process {
         entities.with_entity_removed(id, &mut processor);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Entity, EntityMap, MAIN_PLAYER_ENTITY_ID};
+
+    #[test]
+    fn test_removed_ids_become_stale_when_their_slots_are_reused() {
+        let mut entities = EntityMap::new_ex(Entity::default(), 0);
+        let old_id = entities.new_id();
+        entities.insert(old_id, Entity::default());
+        assert!(entities.remove(old_id).is_some());
+        assert!(entities.is_stale(old_id));
+
+        let new_id = entities.new_id();
+        entities.insert(new_id, Entity::default());
+        assert_ne!(old_id, new_id);
+        assert!(entities.get(old_id).is_none());
+        assert!(entities.get(new_id).is_some());
+        assert!(!entities.is_stale(MAIN_PLAYER_ENTITY_ID));
+    }
+
+    #[test]
+    fn test_ids_of_entities_removed_by_level_changes_stay_reserved() {
+        let mut entities = EntityMap::new_ex(Entity::default(), 0);
+        let id = entities.new_id();
+        entities.insert(id, Entity::default());
+        let removed = entities.remove_all_except_main_player();
+        assert_eq!(removed.len(), 1);
+        assert!(!entities.is_stale(id));
+        assert_ne!(entities.new_id(), id);
+        entities.insert(id, removed[0].1);
+        assert_eq!(entities.len(), 2);
+    }
+
+    #[test]
+    fn test_removing_an_id_twice_only_frees_its_slot_once() {
+        let mut entities = EntityMap::new_ex(Entity::default(), 0);
+        let id = entities.new_id();
+        entities.insert(id, Entity::default());
+        assert!(entities.remove(id).is_some());
+        assert!(entities.remove(id).is_none());
+        let first_id = entities.new_id();
+        let second_id = entities.new_id();
+        assert!(!entities.is_stale(first_id));
+        assert!(!entities.is_stale(second_id));
+    }
+}
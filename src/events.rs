@@ -0,0 +1,62 @@
+use uuid::Uuid;
+
+pub use crate::pickups::PickupType;
+
+/// Something noteworthy that happened during a frame of gameplay.
+///
+/// Systems add these to the level runtime's event queue as they run, rather
+/// than directly triggering side effects like sound effects. Anything that
+/// cares about them, like the audio, HUD, or analytics, can then look at the
+/// queue once the frame is over (see `LevelRuntime::events()`).
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    /// The player with the given id picked up a pickup, which has been removed
+    /// from the level.
+    PickupCollected {
+        player_id: u64,
+        pickup_id: u64,
+        pickup_iid: Option<Uuid>,
+        kind: PickupType,
+    },
+    SwitchToggled {
+        id: u64,
+        iid: Option<Uuid>,
+        /// The identifier of the level the switch is in, so that its entity in
+        /// the level data can be found even if the player leaves the level.
+        level: String,
+        is_switched_on: bool,
+        /// Whether this is the first time the switch has ever been switched on.
+        is_first_time_on: bool,
+        /// Whether the switch started moving something along its route.
+        started_route: bool,
+    },
+    /// An entity was removed from the level by something other than being
+    /// picked up, e.g. by a trigger.
+    EntityDestroyed {
+        id: u64,
+        iid: Option<Uuid>,
+    },
+    /// The main player moved from one level to another.
+    LevelChanged {
+        from: String,
+        to: String,
+    },
+    PlayerLanded {
+        id: u64,
+    },
+    PlayerJumped {
+        id: u64,
+    },
+    MushroomRezzed {
+        id: u64,
+        iid: Option<Uuid>,
+    },
+    Attached {
+        passenger_id: u64,
+        carrier_id: u64,
+    },
+    Detached {
+        passenger_id: u64,
+        carrier_id: u64,
+    },
+}
@@ -0,0 +1,43 @@
+use std::{fs, time::SystemTime};
+
+/// How often we check whether the watched file has changed, in seconds.
+const POLL_INTERVAL_SECONDS: f64 = 0.5;
+
+/// Watches a file on disk for changes by periodically polling its
+/// modification time, which is good enough for hot-reloading assets
+/// during development.
+pub struct FileWatcher {
+    path: String,
+    modified: Option<SystemTime>,
+    last_poll: f64,
+}
+
+impl FileWatcher {
+    pub fn new(path: &str, now: f64) -> Self {
+        FileWatcher {
+            path: path.to_owned(),
+            modified: modified_time(path),
+            last_poll: now,
+        }
+    }
+
+    /// Returns whether the file has been modified since the last time this
+    /// returned true (or since the watcher was created).
+    pub fn has_changed(&mut self, now: f64) -> bool {
+        if now - self.last_poll < POLL_INTERVAL_SECONDS {
+            return false;
+        }
+        self.last_poll = now;
+        let modified = modified_time(&self.path);
+        if modified.is_some() && modified != self.modified {
+            self.modified = modified;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
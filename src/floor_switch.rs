@@ -1,7 +1,7 @@
 use macroquad::prelude::{Rect, BLACK, WHITE};
 
 use crate::{
-    config::config,
+    context::GameContext,
     entity::{Entity, EntityMap},
     math_util::contract_rect,
     physics::PhysicsComponent,
@@ -9,14 +9,18 @@ use crate::{
     switch::{SwitchComponent, TriggerType},
 };
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct FloorSwitchComponent();
 
-pub fn create_floor_switch(start_rect: Rect, trigger_entity: Option<u64>) -> Entity {
+pub fn create_floor_switch(
+    start_rect: Rect,
+    trigger_entity: Option<u64>,
+    ctx: &GameContext,
+) -> Entity {
     let start_point = start_rect.point();
     let mut relative_bbox = start_rect.offset(-start_point);
-    let drawn_rect = contract_rect(&relative_bbox, config().sprite_scale * 4.);
-    relative_bbox.y -= config().sprite_scale * 2.;
+    let drawn_rect = contract_rect(&relative_bbox, ctx.config.sprite_scale * 4.);
+    relative_bbox.y -= ctx.config.sprite_scale * 2.;
     return Entity {
         sprite: SpriteComponent {
             pos: start_point,
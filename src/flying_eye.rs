@@ -2,9 +2,9 @@ use macroquad::prelude::{Rect, Vec2};
 
 use crate::{
     attachment::AttachableComponent,
-    config::config,
+    context::GameContext,
     entity::{Entity, EntityMap},
-    game_assets::game_assets,
+    game_assets::ImageId,
     materials::replace_colors_with_image,
     physics::{PhysicsCollisionBehavior, PhysicsComponent},
     sprite_component::{LeftFacingRendering, SpriteComponent},
@@ -12,22 +12,22 @@ use crate::{
     time::GameTime,
 };
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct FlyingEyeComponent();
 
-pub fn create_flying_eye(start_rect: Rect, base_velocity: Vec2) -> Entity {
-    let assets = &game_assets().flying_eye;
+pub fn create_flying_eye(start_rect: Rect, base_velocity: Vec2, ctx: &GameContext) -> Entity {
+    let assets = &ctx.assets.flying_eye;
     Entity {
         sprite: SpriteComponent {
             base_relative_bbox: assets.flight_bbox,
-            sprite: Some(&assets.flight),
+            sprite: Some(assets.flight.handle()),
             left_facing_rendering: LeftFacingRendering::FlipBoundingBox,
-            material: replace_colors_with_image(&assets.color_replacements),
+            material: replace_colors_with_image(ImageId::FlyingEyeColorReplacements),
             ..Default::default()
         }
         .at_top_left(&start_rect),
         physics: PhysicsComponent {
-            velocity: base_velocity * config().flying_eye_speed,
+            velocity: base_velocity * ctx.config.flying_eye_speed,
             defies_gravity: true,
             collision_behavior: PhysicsCollisionBehavior::ReverseDirectionXY,
             ..Default::default()
@@ -6,8 +6,6 @@ use macroquad::{
     texture::{draw_texture_ex, DrawTextureParams, Texture2D},
 };
 
-use crate::config::config;
-
 /// ASCII value to subtract from every ASCII character we're asked to print.
 const CHAR_CODE_OFFSET: u32 = 32;
 
@@ -16,18 +14,19 @@ pub struct BitmapFont {
     pub char_width: u32,
     pub char_height: u32,
     pub chars_per_line: u32,
+    pub scale: f32,
 }
 
 impl BitmapFont {
     pub fn draw_centered_text<T: AsRef<str>>(&self, text: T, x: f32, y: f32, color: Color) {
-        let scale = config().sprite_scale;
+        let scale = self.scale;
         let line_width = (self.char_width * text.as_ref().len() as u32) as f32 * scale;
         let line_height = self.char_height as f32 * scale;
         self.draw_text(text, x - line_width / 2., y - line_height / 2., color);
     }
 
     pub fn draw_text<T: AsRef<str>>(&self, text: T, x: f32, y: f32, color: Color) {
-        let scale = config().sprite_scale;
+        let scale = self.scale;
         let scaled_size = Vec2::new(
             self.char_width as f32 * scale,
             self.char_height as f32 * scale,
@@ -3,12 +3,15 @@ use std::collections::HashMap;
 use anyhow::{anyhow, Result};
 use macroquad::{
     prelude::Rect,
-    texture::{load_image, load_texture, FilterMode, Image, Texture2D},
+    texture::{Image, Texture2D},
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
     aseprite::load_aseprite_slices,
-    audio::{load_sound_effect, SoundEffect},
+    asset_loader::AssetLoader,
+    audio::SoundEffect,
+    config::Config,
     font::BitmapFont,
     materials::{load_game_materials, GameMaterials},
     sprite_renderer::SpriteRenderer,
@@ -62,13 +65,82 @@ pub struct GameAssets {
     pub gem: GemAssets,
     pub tileset: Texture2D,
     pub font: BitmapFont,
-    pub materials: GameMaterials,
+    /// This will be `None` if we're running headless.
+    pub materials: Option<GameMaterials>,
     pub attach_sound: SoundEffect,
     pub switch_sound: SoundEffect,
     pub found_secret_sound: SoundEffect,
     pub music: SoundEffect,
 }
 
+/// Identifies one of the sprite renderers in our assets, so that entities can
+/// refer to it (and be serialized) without borrowing from the assets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpriteRendererId {
+    #[serde(rename = "huntress.idle")]
+    HuntressIdle,
+    #[serde(rename = "huntress.run")]
+    HuntressRun,
+    #[serde(rename = "huntress.jump")]
+    HuntressJump,
+    #[serde(rename = "huntress.fall")]
+    HuntressFall,
+    #[serde(rename = "flying_eye.flight")]
+    FlyingEyeFlight,
+    #[serde(rename = "mushroom.death")]
+    MushroomDeath,
+    #[serde(rename = "mushroom.run")]
+    MushroomRun,
+    #[serde(rename = "spear.spear_move")]
+    SpearMove,
+    #[serde(rename = "gem.gem")]
+    Gem,
+}
+
+/// Identifies one of the images in our assets, so that entities can refer to
+/// it (and be serialized) without borrowing from the assets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImageId {
+    #[serde(rename = "huntress.no_spear_color_replacements")]
+    HuntressNoSpearColorReplacements,
+    #[serde(rename = "huntress.spear_glow_color_replacements")]
+    HuntressSpearGlowColorReplacements,
+    #[serde(rename = "flying_eye.color_replacements")]
+    FlyingEyeColorReplacements,
+    #[serde(rename = "mushroom.color_replacements")]
+    MushroomColorReplacements,
+    #[serde(rename = "mushroom.dead_color_replacements")]
+    MushroomDeadColorReplacements,
+}
+
+impl GameAssets {
+    pub fn sprite_renderer(&self, id: SpriteRendererId) -> &SpriteRenderer {
+        match id {
+            SpriteRendererId::HuntressIdle => &self.huntress.idle,
+            SpriteRendererId::HuntressRun => &self.huntress.run,
+            SpriteRendererId::HuntressJump => &self.huntress.jump,
+            SpriteRendererId::HuntressFall => &self.huntress.fall,
+            SpriteRendererId::FlyingEyeFlight => &self.flying_eye.flight,
+            SpriteRendererId::MushroomDeath => &self.mushroom.death,
+            SpriteRendererId::MushroomRun => &self.mushroom.run,
+            SpriteRendererId::SpearMove => &self.spear.spear_move,
+            SpriteRendererId::Gem => &self.gem.gem,
+        }
+    }
+
+    pub fn image(&self, id: ImageId) -> &Image {
+        match id {
+            ImageId::HuntressNoSpearColorReplacements => &self.huntress.no_spear_color_replacements,
+            ImageId::HuntressSpearGlowColorReplacements => {
+                &self.huntress.spear_glow_color_replacements
+            }
+            ImageId::FlyingEyeColorReplacements => &self.flying_eye.color_replacements,
+            ImageId::MushroomColorReplacements => &self.mushroom.color_replacements,
+            ImageId::MushroomDeadColorReplacements => &self.mushroom.dead_color_replacements,
+        }
+    }
+}
+
 fn get_slice(slices: &HashMap<String, Rect>, name: &str) -> Result<Rect> {
     if let Some(&slice) = slices.get(name) {
         Ok(slice)
@@ -77,95 +149,152 @@ fn get_slice(slices: &HashMap<String, Rect>, name: &str) -> Result<Rect> {
     }
 }
 
-async fn load_pixel_perfect_texture(path: &str) -> Result<Texture2D> {
-    let texture = load_texture(path).await?;
-    texture.set_filter(FilterMode::Nearest);
-    Ok(texture)
-}
-
-pub async fn load_game_assets() -> Result<()> {
-    let mushroom_idle_slices = load_aseprite_slices("media/Mushroom/Idle.json").await?;
-    let mushroom_death_slices = load_aseprite_slices("media/Mushroom/Death.json").await?;
-    let huntress_idle_slices = load_aseprite_slices("media/Huntress/Idle.json").await?;
-    let assets = GameAssets {
+pub async fn load_game_assets(config: &Config, loader: AssetLoader) -> Result<GameAssets> {
+    let scale = config.sprite_scale;
+    let mushroom_idle_slices =
+        load_aseprite_slices(&loader, "media/Mushroom/Idle.json", scale).await?;
+    let mushroom_death_slices =
+        load_aseprite_slices(&loader, "media/Mushroom/Death.json", scale).await?;
+    let huntress_idle_slices =
+        load_aseprite_slices(&loader, "media/Huntress/Idle.json", scale).await?;
+    Ok(GameAssets {
         huntress: HuntressAssets {
-            idle: SpriteRenderer::new(load_texture("media/Huntress/Idle.png").await?, 8),
-            run: SpriteRenderer::new(load_texture("media/Huntress/Run.png").await?, 8),
-            jump: SpriteRenderer::new(load_texture("media/Huntress/Jump.png").await?, 2),
-            fall: SpriteRenderer::new(load_texture("media/Huntress/Fall.png").await?, 2),
+            idle: loader
+                .load_sprite_renderer(
+                    SpriteRendererId::HuntressIdle,
+                    "media/Huntress/Idle.png",
+                    8,
+                    scale,
+                )
+                .await?,
+            run: loader
+                .load_sprite_renderer(
+                    SpriteRendererId::HuntressRun,
+                    "media/Huntress/Run.png",
+                    8,
+                    scale,
+                )
+                .await?,
+            jump: loader
+                .load_sprite_renderer(
+                    SpriteRendererId::HuntressJump,
+                    "media/Huntress/Jump.png",
+                    2,
+                    scale,
+                )
+                .await?,
+            fall: loader
+                .load_sprite_renderer(
+                    SpriteRendererId::HuntressFall,
+                    "media/Huntress/Fall.png",
+                    2,
+                    scale,
+                )
+                .await?,
             idle_bbox: get_slice(&huntress_idle_slices, "idle_bounding_box")?,
             spear_point_bbox: get_slice(&huntress_idle_slices, "spear_point_bounding_box")?,
-            no_spear_color_replacements: load_image(
-                "media/Huntress/no_spear_color_replacements.png",
-            )
-            .await?,
-            spear_glow_color_replacements: load_image(
-                "media/Huntress/spear_glow_color_replacements.png",
-            )
-            .await?,
-            jump_sound: load_sound_effect("media/audio/Jump.ogg").await?,
+            no_spear_color_replacements: loader
+                .load_image("media/Huntress/no_spear_color_replacements.png")
+                .await?,
+            spear_glow_color_replacements: loader
+                .load_image("media/Huntress/spear_glow_color_replacements.png")
+                .await?,
+            jump_sound: loader.load_sound_effect("media/audio/Jump.ogg").await?,
         },
         flying_eye: FlyingEyeAssets {
-            flight: SpriteRenderer::new(load_texture("media/FlyingEye/Flight.png").await?, 8),
+            flight: loader
+                .load_sprite_renderer(
+                    SpriteRendererId::FlyingEyeFlight,
+                    "media/FlyingEye/Flight.png",
+                    8,
+                    scale,
+                )
+                .await?,
             flight_bbox: get_slice(
-                &load_aseprite_slices("media/FlyingEye/Flight.json").await?,
+                &load_aseprite_slices(&loader, "media/FlyingEye/Flight.json", scale).await?,
                 "flight_bounding_box",
             )?,
-            color_replacements: load_image("media/FlyingEye/color_replacements.png").await?,
+            color_replacements: loader
+                .load_image("media/FlyingEye/color_replacements.png")
+                .await?,
         },
-        attach_sound: load_sound_effect("media/audio/Attach.ogg").await?,
-        switch_sound: load_sound_effect("media/audio/Switch.ogg").await?,
-        found_secret_sound: load_sound_effect("media/audio/FoundSecret.ogg").await?,
-        music: load_sound_effect("media/audio/music/Ditty-2023-01-07.ogg").await?,
+        attach_sound: loader.load_sound_effect("media/audio/Attach.ogg").await?,
+        switch_sound: loader.load_sound_effect("media/audio/Switch.ogg").await?,
+        found_secret_sound: loader
+            .load_sound_effect("media/audio/FoundSecret.ogg")
+            .await?,
+        music: loader
+            .load_sound_effect("media/audio/music/Ditty-2023-01-07.ogg")
+            .await?,
         mushroom: MushroomAssets {
-            death: SpriteRenderer::new(load_texture("media/Mushroom/Death.png").await?, 4),
+            death: loader
+                .load_sprite_renderer(
+                    SpriteRendererId::MushroomDeath,
+                    "media/Mushroom/Death.png",
+                    4,
+                    scale,
+                )
+                .await?,
             idle_bbox: get_slice(&mushroom_idle_slices, "idle_bounding_box")?,
             platform_bbox: get_slice(&mushroom_idle_slices, "platform_bounding_box")?,
             dead_bbox: get_slice(&mushroom_death_slices, "dead_bounding_box")?,
-            run: SpriteRenderer::new(load_texture("media/Mushroom/Run.png").await?, 8),
-            color_replacements: load_image("media/Mushroom/color_replacements.png").await?,
-            dead_color_replacements: load_image("media/Mushroom/dead_color_replacements.png")
+            run: loader
+                .load_sprite_renderer(
+                    SpriteRendererId::MushroomRun,
+                    "media/Mushroom/Run.png",
+                    8,
+                    scale,
+                )
+                .await?,
+            color_replacements: loader
+                .load_image("media/Mushroom/color_replacements.png")
+                .await?,
+            dead_color_replacements: loader
+                .load_image("media/Mushroom/dead_color_replacements.png")
+                .await?,
+            rez_sound: loader
+                .load_sound_effect("media/audio/MushroomRez.ogg")
                 .await?,
-            rez_sound: load_sound_effect("media/audio/MushroomRez.ogg").await?,
         },
         spear: SpearAssets {
-            spear_move: SpriteRenderer::new(
-                load_texture("media/Huntress/Spear move.png").await?,
-                4,
-            ),
+            spear_move: loader
+                .load_sprite_renderer(
+                    SpriteRendererId::SpearMove,
+                    "media/Huntress/Spear move.png",
+                    4,
+                    scale,
+                )
+                .await?,
             spear_move_bbox: get_slice(
-                &load_aseprite_slices("media/Huntress/Spear move.json").await?,
+                &load_aseprite_slices(&loader, "media/Huntress/Spear move.json", scale).await?,
                 "spear_bounding_box",
             )?,
-            pickup_sound: load_sound_effect("media/audio/SpearPickup.ogg").await?,
+            pickup_sound: loader
+                .load_sound_effect("media/audio/SpearPickup.ogg")
+                .await?,
         },
         gem: GemAssets {
-            gem: SpriteRenderer::new(load_texture("media/gem.png").await?, 1),
-            pickup_sound: load_sound_effect("media/audio/GemPickup.ogg").await?,
+            gem: loader
+                .load_sprite_renderer(SpriteRendererId::Gem, "media/gem.png", 1, scale)
+                .await?,
+            pickup_sound: loader.load_sound_effect("media/audio/GemPickup.ogg").await?,
         },
-        tileset: load_pixel_perfect_texture("media/bigbrick1.png").await?,
+        tileset: loader
+            .load_pixel_perfect_texture("media/bigbrick1.png")
+            .await?,
         font: BitmapFont {
-            texture: load_pixel_perfect_texture("media/pman_font01.png").await?,
+            texture: loader
+                .load_pixel_perfect_texture("media/pman_font01.png")
+                .await?,
             char_width: 6,
             char_height: 8,
             chars_per_line: 16,
+            scale,
         },
-        materials: load_game_materials().await?,
-    };
-
-    unsafe {
-        GAME_ASSETS = Some(assets);
-    }
-
-    Ok(())
-}
-
-pub fn game_assets() -> &'static GameAssets {
-    unsafe {
-        GAME_ASSETS
-            .as_ref()
-            .expect("load_game_assets() was not called or did not finish")
-    }
+        materials: if loader.is_headless() {
+            None
+        } else {
+            Some(load_game_materials().await?)
+        },
+    })
 }
-
-static mut GAME_ASSETS: Option<GameAssets> = None;
@@ -0,0 +1,77 @@
+use crate::input::Buttons;
+
+/// How far an analog stick needs to be pushed before we consider it to be
+/// pressing a direction.
+#[cfg(any(target_arch = "wasm32", feature = "gamepad"))]
+const STICK_THRESHOLD: f32 = 0.5;
+
+/// Reads buttons from any connected gamepads.
+///
+/// On the web this uses the browser's Gamepad API. Native builds only support
+/// gamepads when the `gamepad` feature is enabled, since it requires extra
+/// system libraries on some platforms (e.g. libudev on Linux).
+pub struct Gamepads {
+    #[cfg(all(not(target_arch = "wasm32"), feature = "gamepad"))]
+    gilrs: Option<gilrs::Gilrs>,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        Gamepads {
+            #[cfg(all(not(target_arch = "wasm32"), feature = "gamepad"))]
+            gilrs: gilrs::Gilrs::new()
+                .map_err(|e| println!("Unable to initialize gamepad support: {}", e))
+                .ok(),
+        }
+    }
+
+    /// The buttons currently held down on any connected gamepad.
+    #[cfg(target_arch = "wasm32")]
+    pub fn buttons(&mut self) -> Buttons {
+        use crate::js_interop::js_interop_wasm32::{gamepad_state, GamepadButton};
+
+        let mut buttons = Buttons::empty();
+        let gamepad = gamepad_state();
+        if gamepad.is_down(GamepadButton::DPadLeft) || gamepad.stick_x < -STICK_THRESHOLD {
+            buttons |= Buttons::LEFT;
+        }
+        if gamepad.is_down(GamepadButton::DPadRight) || gamepad.stick_x > STICK_THRESHOLD {
+            buttons |= Buttons::RIGHT;
+        }
+        if gamepad.is_down(GamepadButton::South) {
+            buttons |= Buttons::JUMP;
+        }
+        buttons
+    }
+
+    /// The buttons currently held down on any connected gamepad.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "gamepad"))]
+    pub fn buttons(&mut self) -> Buttons {
+        use gilrs::{Axis, Button};
+
+        let mut buttons = Buttons::empty();
+        let Some(gilrs) = self.gilrs.as_mut() else {
+            return buttons;
+        };
+        // Gamepad state is only updated as events are processed.
+        while gilrs.next_event().is_some() {}
+        for (_id, gamepad) in gilrs.gamepads() {
+            let stick_x = gamepad.value(Axis::LeftStickX);
+            if gamepad.is_pressed(Button::DPadLeft) || stick_x < -STICK_THRESHOLD {
+                buttons |= Buttons::LEFT;
+            }
+            if gamepad.is_pressed(Button::DPadRight) || stick_x > STICK_THRESHOLD {
+                buttons |= Buttons::RIGHT;
+            }
+            if gamepad.is_pressed(Button::South) {
+                buttons |= Buttons::JUMP;
+            }
+        }
+        buttons
+    }
+
+    #[cfg(all(not(target_arch = "wasm32"), not(feature = "gamepad")))]
+    pub fn buttons(&mut self) -> Buttons {
+        Buttons::empty()
+    }
+}
@@ -1,34 +1,17 @@
 use macroquad::window::{screen_height, screen_width};
 
-use crate::{
-    config::config,
-    entity::EntityMap,
-    game_assets::game_assets,
-    level::{EntityKind, Level},
-};
+use crate::{entity::EntityMap, level::Level, world::World};
 
 use std::fmt::Write;
 
-fn count_total_and_remaining_gems(entities: &EntityMap, level: &Level) -> (u32, u32) {
-    let mut total = 0;
-    let mut remaining = 0;
-
-    for entity in level.entities.values() {
-        if matches!(entity.kind, EntityKind::Gem) {
-            if entities.get_id_for_iid(entity.iid).is_some() {
-                remaining += 1;
-            }
-            total += 1;
-        }
-    }
-
-    (total, remaining)
-}
-
-pub fn draw_gem_counter(entities: &EntityMap, level: &Level) {
-    let (total, remaining) = count_total_and_remaining_gems(entities, level);
+pub fn draw_gem_counter(entities: &EntityMap, level: &Level, world: &World) {
+    let Some(player) = entities.main_player().player else {
+        return;
+    };
+    let total = world.total_gems(level);
+    let collected = player.inventory.gems;
 
-    if total == remaining {
+    if collected == 0 {
         // Don't show the gem counter if there are no gems to collect.
         //
         // But also, don't show it if the player has no gems: they might
@@ -38,21 +21,12 @@ pub fn draw_gem_counter(entities: &EntityMap, level: &Level) {
     }
 
     let mut string: heapless::String<100> = heapless::String::new();
-    let font = &game_assets().font;
+    let ctx = world.ctx();
+    let font = &ctx.assets.font;
 
-    if remaining == 0 {
-        write!(
-            string,
-            "You collected all the gems! You can stop playing now."
-        )
-        .unwrap();
-    } else if remaining == 1 {
-        write!(string, "One gem remains.").unwrap();
-    } else {
-        write!(string, "{} gems remain.", remaining).unwrap();
-    }
+    write!(string, "{}/{} gems", collected, total).unwrap();
 
-    let scale = config().sprite_scale;
+    let scale = ctx.config.sprite_scale;
     let line_height = (font.char_height as f32) * scale;
     let string_width = (font.char_width as f32) * scale * string.len() as f32;
 
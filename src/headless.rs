@@ -0,0 +1,147 @@
+use std::{
+    future::Future,
+    rc::Rc,
+    sync::Arc,
+    task::{Context, Poll, Wake},
+};
+
+use anyhow::{anyhow, Result};
+use macroquad::prelude::Vec2;
+
+use crate::{
+    asset_loader::AssetLoader,
+    checksum::{ChecksumChannel, Desync},
+    cli::Cli,
+    context::GameContext,
+    outcomes::SessionOutcomes,
+    recorder::{InputPlayer, Recording, RecordingMetadata},
+    simulation::Simulation,
+    world::World,
+};
+
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+/// Run the given future to completion on the current thread.
+///
+/// This only supports futures that never actually need to wait on anything,
+/// such as the ones returned by [`AssetLoader::Headless`].
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Arc::new(NoopWaker).into();
+    let mut context = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    match future.as_mut().poll(&mut context) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("Future unexpectedly blocked while running headless!"),
+    }
+}
+
+/// Load the config, game assets, and world without needing a window or GPU.
+///
+/// The returned world owns everything it was loaded with, so this can be
+/// called multiple times, e.g. to compare different configs.
+pub fn load_headless(config_path: &str, world_path: &str) -> Result<Rc<World>> {
+    let loader = AssetLoader::Headless;
+    block_on(async {
+        // Command-line arguments only affect things like the screen size, which
+        // doesn't matter when we're headless, so just use the defaults.
+        let ctx = GameContext::load(config_path, &Cli::default(), loader).await?;
+        Ok(Rc::new(World::load(world_path, ctx, &loader).await?))
+    })
+}
+
+/// The results of replaying a recording headlessly.
+pub struct ReplaySummary {
+    pub frames: u64,
+    pub deaths: u32,
+    pub outcomes: SessionOutcomes,
+    pub final_level: String,
+    pub final_player_pos: Vec2,
+    pub first_desync: Option<Desync>,
+    fixed_fps: u64,
+}
+
+impl ReplaySummary {
+    pub fn seconds(&self) -> f64 {
+        self.frames as f64 / self.fixed_fps as f64
+    }
+}
+
+/// Replay the given recording from the given start position as fast as possible.
+///
+/// The recording is played back in the given `world`, which should be the one
+/// it was made in; if it isn't, playback will likely diverge.
+pub fn replay_recording(
+    recording: Recording,
+    start_position: &str,
+    world: Rc<World>,
+) -> Result<ReplaySummary> {
+    let metadata = RecordingMetadata::current(start_position, &world);
+    let checksums = ChecksumChannel::new_shared(0);
+    let input_player = InputPlayer::from_recording(recording, &metadata, checksums.clone())?;
+    let mut simulation = Simulation::new(start_position, world).with_checksums(checksums.clone());
+    simulation.run(input_player);
+
+    let runtime = simulation.level_runtime();
+    let first_desync = checksums.borrow().first_desync();
+    Ok(ReplaySummary {
+        frames: simulation.frames_so_far(),
+        deaths: simulation.deaths(),
+        outcomes: simulation.outcomes().clone(),
+        final_level: runtime.level().identifier.clone(),
+        final_player_pos: runtime.entities().main_player().sprite.pos,
+        first_desync,
+        fixed_fps: runtime.ctx().config.fixed_fps,
+    })
+}
+
+pub fn run_headless(args: &Cli) -> Result<()> {
+    let Some(filename) = &args.playback else {
+        return Err(anyhow!("--headless requires --playback"));
+    };
+    let world = load_headless(crate::CONFIG_PATH, crate::WORLD_PATH)?;
+
+    println!("Playing back recording from '{}' headlessly.", filename);
+    let recording = Recording::parse(&std::fs::read(filename)?)?;
+    let summary = replay_recording(recording, &args.start_position, world)?;
+
+    println!(
+        "Simulated {} frames ({:.2} seconds of game time).",
+        summary.frames,
+        summary.seconds()
+    );
+    println!("Main player died {} time(s).", summary.deaths);
+    println!(
+        "Main player ended in level '{}' at ({:.2}, {:.2}).",
+        summary.final_level, summary.final_player_pos.x, summary.final_player_pos.y
+    );
+    let outcomes = &summary.outcomes;
+    println!(
+        "Main player reached {} level(s), the furthest being '{}'.",
+        outcomes.levels_reached(),
+        outcomes.furthest_level().unwrap_or_default()
+    );
+    println!(
+        "Main player collected {} gem(s), rezzed {} mushroom(s), and {} the spear.",
+        outcomes.gems_collected(),
+        outcomes.mushrooms_rezzed(),
+        if outcomes.picked_up_spear() {
+            "picked up"
+        } else {
+            "did not pick up"
+        }
+    );
+    if let Some(desync) = summary.first_desync {
+        println!(
+            "Replay diverged from recording at frame {} (expected checksum {:016x}, got {:016x}).",
+            desync.frame_number, desync.expected_checksum, desync.actual_checksum
+        );
+    } else {
+        println!("Replay did not diverge from any checksums in the recording.");
+    }
+
+    Ok(())
+}
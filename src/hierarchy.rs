@@ -1,6 +1,6 @@
 use crate::entity::{filter_and_process_entities, EntityMap};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct ChildComponent {
     /// The ID of the parent entity.
     pub parent: u64,
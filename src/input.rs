@@ -1,6 +1,10 @@
+use std::collections::HashMap;
+
 use bitflags::bitflags;
 use macroquad::prelude::{is_key_down, KeyCode};
 
+use crate::{gamepad::Gamepads, touch_controls::touch_buttons};
+
 bitflags! {
     #[derive(Serialize, Deserialize)]
     pub struct Buttons: u32 {
@@ -10,30 +14,171 @@ bitflags! {
     }
 }
 
-impl Default for Buttons {
+/// The names of all the buttons, for use in human-readable formats.
+const BUTTON_NAMES: [(Buttons, &str); 3] = [
+    (Buttons::LEFT, "LEFT"),
+    (Buttons::RIGHT, "RIGHT"),
+    (Buttons::JUMP, "JUMP"),
+];
+
+/// The names of the keys that can be bound to buttons in the config.
+const KEY_NAMES: [(&str, KeyCode); 52] = [
+    ("A", KeyCode::A),
+    ("B", KeyCode::B),
+    ("C", KeyCode::C),
+    ("D", KeyCode::D),
+    ("E", KeyCode::E),
+    ("F", KeyCode::F),
+    ("G", KeyCode::G),
+    ("H", KeyCode::H),
+    ("I", KeyCode::I),
+    ("J", KeyCode::J),
+    ("K", KeyCode::K),
+    ("L", KeyCode::L),
+    ("M", KeyCode::M),
+    ("N", KeyCode::N),
+    ("O", KeyCode::O),
+    ("P", KeyCode::P),
+    ("Q", KeyCode::Q),
+    ("R", KeyCode::R),
+    ("S", KeyCode::S),
+    ("T", KeyCode::T),
+    ("U", KeyCode::U),
+    ("V", KeyCode::V),
+    ("W", KeyCode::W),
+    ("X", KeyCode::X),
+    ("Y", KeyCode::Y),
+    ("Z", KeyCode::Z),
+    ("0", KeyCode::Key0),
+    ("1", KeyCode::Key1),
+    ("2", KeyCode::Key2),
+    ("3", KeyCode::Key3),
+    ("4", KeyCode::Key4),
+    ("5", KeyCode::Key5),
+    ("6", KeyCode::Key6),
+    ("7", KeyCode::Key7),
+    ("8", KeyCode::Key8),
+    ("9", KeyCode::Key9),
+    ("Space", KeyCode::Space),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("LeftShift", KeyCode::LeftShift),
+    ("RightShift", KeyCode::RightShift),
+    ("LeftControl", KeyCode::LeftControl),
+    ("RightControl", KeyCode::RightControl),
+    ("LeftAlt", KeyCode::LeftAlt),
+    ("RightAlt", KeyCode::RightAlt),
+    ("Tab", KeyCode::Tab),
+    ("Comma", KeyCode::Comma),
+    ("Period", KeyCode::Period),
+    ("Slash", KeyCode::Slash),
+    ("Semicolon", KeyCode::Semicolon),
+];
+
+/// Keys that the game handles itself (see `main.rs`), along with what they do.
+/// These can't be bound to buttons, since pressing them would do both things.
+const RESERVED_KEYS: [(KeyCode, &str); 7] = [
+    (KeyCode::R, "rewinding"),
+    (KeyCode::G, "toggling debug mode"),
+    (KeyCode::Q, "quitting"),
+    (KeyCode::Key1, "selecting save slots"),
+    (KeyCode::Key2, "selecting save slots"),
+    (KeyCode::Key3, "selecting save slots"),
+    (KeyCode::Key4, "selecting save slots"),
+];
+
+/// Which keys are bound to which buttons. Any number of keys can be bound to
+/// the same button.
+///
+/// In the config, this is an object mapping button names (as returned by
+/// `Buttons::to_names()`) to lists of key names (as defined in `KEY_NAMES`).
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "HashMap<String, Vec<String>>")]
+pub struct KeyBindings {
+    bindings: Vec<(KeyCode, Buttons)>,
+}
+
+impl KeyBindings {
+    /// The buttons whose keys are currently held down.
+    pub fn buttons_down(&self) -> Buttons {
+        let mut buttons = Buttons::empty();
+        for &(key_code, button) in self.bindings.iter() {
+            if is_key_down(key_code) {
+                buttons |= button;
+            }
+        }
+        buttons
+    }
+}
+
+impl Default for KeyBindings {
     fn default() -> Self {
-        Buttons::empty()
+        KeyBindings {
+            bindings: vec![
+                (KeyCode::A, Buttons::LEFT),
+                (KeyCode::D, Buttons::RIGHT),
+                (KeyCode::Space, Buttons::JUMP),
+            ],
+        }
     }
 }
 
-fn key_to_button(key_code: KeyCode, button: Buttons) -> Buttons {
-    if is_key_down(key_code) {
-        button
-    } else {
-        Buttons::empty()
+impl TryFrom<HashMap<String, Vec<String>>> for KeyBindings {
+    type Error = String;
+
+    fn try_from(value: HashMap<String, Vec<String>>) -> Result<Self, Self::Error> {
+        let mut bindings = vec![];
+        for (button_name, key_names) in value {
+            let Some(button) = Buttons::from_name(&button_name) else {
+                return Err(format!("Unknown button: {}", button_name));
+            };
+            for key_name in key_names {
+                let Some(&(_, key_code)) = KEY_NAMES.iter().find(|(name, _)| *name == key_name)
+                else {
+                    return Err(format!("Unknown key: {}", key_name));
+                };
+                if let Some((_, purpose)) = RESERVED_KEYS.iter().find(|(key, _)| *key == key_code) {
+                    return Err(format!("Key {} is reserved for {}", key_name, purpose));
+                }
+                bindings.push((key_code, button));
+            }
+        }
+        Ok(KeyBindings { bindings })
     }
 }
 
-impl Buttons {
-    pub fn from_macroquad() -> Self {
-        key_to_button(KeyCode::A, Buttons::LEFT)
-            | key_to_button(KeyCode::D, Buttons::RIGHT)
-            | key_to_button(KeyCode::Space, Buttons::JUMP)
+impl Default for Buttons {
+    fn default() -> Self {
+        Buttons::empty()
     }
+}
 
+impl Buttons {
     pub fn is_down(&self, button: Buttons) -> bool {
         !(*self & button).is_empty()
     }
+
+    /// Iterate through every individual button and its name.
+    pub fn all_named() -> impl Iterator<Item = (Buttons, &'static str)> {
+        BUTTON_NAMES.into_iter()
+    }
+
+    /// The names of the buttons that are down.
+    pub fn to_names(&self) -> Vec<&'static str> {
+        Buttons::all_named()
+            .filter(|(button, _)| self.is_down(*button))
+            .map(|(_, name)| name)
+            .collect()
+    }
+
+    /// Parse the name of an individual button, as returned by `to_names()`.
+    pub fn from_name(name: &str) -> Option<Buttons> {
+        Buttons::all_named()
+            .find(|(_, button_name)| *button_name == name)
+            .map(|(button, _)| button)
+    }
 }
 
 #[derive(Default, Copy, Clone)]
@@ -59,16 +204,85 @@ impl InputState {
 
 pub type InputStream = Box<dyn Iterator<Item = Buttons>>;
 
-struct MacroquadInputStream;
+/// Reads buttons from the keyboard, any connected gamepads, and the on-screen
+/// touch controls.
+struct MacroquadInputStream {
+    key_bindings: KeyBindings,
+    gamepads: Gamepads,
+}
 
 impl Iterator for MacroquadInputStream {
     type Item = Buttons;
 
     fn next(&mut self) -> Option<Self::Item> {
-        Some(Buttons::from_macroquad())
+        Some(self.key_bindings.buttons_down() | self.gamepads.buttons() | touch_buttons())
     }
 }
 
-pub fn create_macroquad_input_stream() -> InputStream {
-    Box::new(MacroquadInputStream)
+pub fn create_macroquad_input_stream(key_bindings: &KeyBindings) -> InputStream {
+    Box::new(MacroquadInputStream {
+        key_bindings: key_bindings.clone(),
+        gamepads: Gamepads::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use macroquad::prelude::KeyCode;
+
+    use super::{Buttons, KeyBindings};
+
+    fn parse(bindings: &[(&str, &[&str])]) -> Result<KeyBindings, String> {
+        let map: HashMap<String, Vec<String>> = bindings
+            .iter()
+            .map(|(button, keys)| {
+                (
+                    button.to_string(),
+                    keys.iter().map(|key| key.to_string()).collect(),
+                )
+            })
+            .collect();
+        KeyBindings::try_from(map)
+    }
+
+    #[test]
+    fn test_key_bindings_work() {
+        let bindings = parse(&[("LEFT", &["A", "Left"]), ("JUMP", &["Space"])]).unwrap();
+        let mut pairs = bindings.bindings.clone();
+        pairs.sort_by_key(|(_, button)| button.bits());
+        assert_eq!(
+            pairs,
+            vec![
+                (KeyCode::A, Buttons::LEFT),
+                (KeyCode::Left, Buttons::LEFT),
+                (KeyCode::Space, Buttons::JUMP),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_key_bindings_reject_unknown_names() {
+        assert_eq!(
+            parse(&[("BOOP", &["A"])]).err(),
+            Some("Unknown button: BOOP".to_owned())
+        );
+        assert_eq!(
+            parse(&[("LEFT", &["Boop"])]).err(),
+            Some("Unknown key: Boop".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_key_bindings_reject_reserved_keys() {
+        assert_eq!(
+            parse(&[("JUMP", &["Space", "R"])]).err(),
+            Some("Key R is reserved for rewinding".to_owned())
+        );
+        assert_eq!(
+            parse(&[("LEFT", &["1"])]).err(),
+            Some("Key 1 is reserved for selecting save slots".to_owned())
+        );
+    }
 }
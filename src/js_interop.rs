@@ -3,14 +3,53 @@ pub mod js_interop_wasm32 {
     use std::{cell::RefCell, io::Write, rc::Rc};
 
     use crate::{
-        input::{create_macroquad_input_stream, InputStream},
-        recorder::{InputPlayer, InputRecorder},
+        checksum::SharedChecksumChannel,
+        input::{create_macroquad_input_stream, InputStream, KeyBindings},
+        recorder::{InputPlayer, InputRecorder, RecordingMetadata},
     };
 
     extern "C" {
         fn init_version(version_str: *const u8);
         fn record_input(data: *const u8, len: usize);
         fn does_browser_support_ogg() -> i32;
+        fn get_save_slots_length() -> usize;
+        fn copy_save_slots(ptr: *mut u8);
+        fn set_save_slots(data: *const u8, len: usize);
+        fn get_gamepad_buttons() -> u32;
+        fn get_gamepad_stick_x() -> f32;
+        fn is_touch_device() -> i32;
+    }
+
+    /// Indices of buttons in the browser's "standard" gamepad mapping, as
+    /// described at https://w3c.github.io/gamepad/#remapping.
+    #[derive(Clone, Copy)]
+    pub enum GamepadButton {
+        South = 0,
+        DPadLeft = 14,
+        DPadRight = 15,
+    }
+
+    /// The combined state of all connected gamepads.
+    pub struct GamepadState {
+        /// A bitmask of the pressed buttons, indexed by `GamepadButton`.
+        pressed_buttons: u32,
+        /// The horizontal position of the left analog stick, from -1 to 1.
+        pub stick_x: f32,
+    }
+
+    impl GamepadState {
+        pub fn is_down(&self, button: GamepadButton) -> bool {
+            self.pressed_buttons & (1 << button as u32) != 0
+        }
+    }
+
+    pub fn gamepad_state() -> GamepadState {
+        unsafe {
+            GamepadState {
+                pressed_buttons: get_gamepad_buttons(),
+                stick_x: get_gamepad_stick_x(),
+            }
+        }
     }
 
     #[derive(Default)]
@@ -34,7 +73,7 @@ pub mod js_interop_wasm32 {
     }
 
     #[no_mangle]
-    extern "C" fn set_blurred(is_blurred: i32) {
+    pub extern "C" fn set_blurred(is_blurred: i32) {
         js_state_mut().is_blurred = is_blurred != 0;
     }
 
@@ -56,19 +95,47 @@ pub mod js_interop_wasm32 {
         unsafe { init_version(version.as_ptr()) }
     }
 
+    pub fn has_touch_screen() -> bool {
+        unsafe { is_touch_device() != 0 }
+    }
+
     pub fn is_ogg_supported() -> bool {
         unsafe { does_browser_support_ogg() != 0 }
     }
 
-    pub fn create_input_stream() -> InputStream {
+    /// Returns the save slots stored in the browser, if any.
+    pub fn load_save_slots() -> Option<String> {
+        let len = unsafe { get_save_slots_length() };
+        if len == 0 {
+            return None;
+        }
+        let mut data = vec![0u8; len];
+        unsafe { copy_save_slots(data.as_mut_ptr()) }
+        String::from_utf8(data).ok()
+    }
+
+    pub fn store_save_slots(data: &str) {
+        unsafe { set_save_slots(data.as_ptr(), data.len()) }
+    }
+
+    pub fn create_input_stream(
+        metadata: &RecordingMetadata,
+        checksums: SharedChecksumChannel,
+        key_bindings: &KeyBindings,
+    ) -> InputStream {
         // THIS IS EXTREMELY STUPID CODE WRITTEN TO MAKE
         // RUST STOP BUGGING ME ABOUT UNUSED CRAP IN THE
         // WASM BUILD
         if false {
-            for _wtf in InputPlayer::new(vec![]) {}
+            for _wtf in InputPlayer::new(vec![], metadata, checksums.clone()).unwrap() {}
         }
 
         let output = Rc::new(RefCell::new(JsWriter()));
-        InputRecorder::new(create_macroquad_input_stream(), output)
+        InputRecorder::new(
+            create_macroquad_input_stream(key_bindings),
+            output,
+            metadata,
+            checksums,
+        )
     }
 }
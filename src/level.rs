@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use anyhow::{anyhow, Error, Result};
 use macroquad::prelude::*;
@@ -6,17 +6,42 @@ use uuid::Uuid;
 
 use crate::{
     audio::SoundEffect,
-    collision::{Collider, CollisionFlags},
-    config::config,
-    game_assets::game_assets,
+    collision::{Collider, ColliderShape, CollisionFlags, Side},
+    context::GameContext,
     ldtk::{self, field_into, EntityRef, FieldInstance, LayerInstance, TileInstance},
     xy_range_iterator::XYRangeIterator,
 };
 
+/// What occupies a grid cell, as far as collisions are concerned. The comment
+/// on each variant is its value in the level's IntGrid layer.
+///
+/// The 22.5° slopes rise one cell for every two cells they run, so each is split
+/// into a lower and an upper half that occupy adjacent cells.
+///
+/// Only solid cells are auto-tiled in LDtk. The tileset doesn't have any art
+/// for one-way platforms or slopes, so their tiles need to be painted by hand
+/// (e.g. on the BackgroundTiles layer), or else they'll be invisible.
 #[derive(Eq, PartialEq)]
 pub enum ColliderType {
+    /// 0
     Empty,
+    /// 1
     Solid,
+    /// 2: A platform that only collides on its top side, so it can be jumped
+    /// through from below or walked through from the side.
+    OneWay,
+    /// 3
+    Slope45UpRight,
+    /// 4
+    Slope45UpLeft,
+    /// 5
+    Slope22UpRightLower,
+    /// 6
+    Slope22UpRightUpper,
+    /// 7
+    Slope22UpLeftUpper,
+    /// 8
+    Slope22UpLeftLower,
 }
 
 #[derive(PartialEq, Deserialize)]
@@ -45,6 +70,65 @@ impl ColliderType {
 
         Ok(result)
     }
+
+    /// If this is a slope, returns its heights at its left and right edges, as
+    /// fractions of the cell's height (see `ColliderShape::Slope`).
+    fn slope_heights(&self) -> Option<(f32, f32)> {
+        match self {
+            ColliderType::Slope45UpRight => Some((0., 1.)),
+            ColliderType::Slope45UpLeft => Some((1., 0.)),
+            ColliderType::Slope22UpRightLower => Some((0., 0.5)),
+            ColliderType::Slope22UpRightUpper => Some((0.5, 1.)),
+            ColliderType::Slope22UpLeftUpper => Some((1., 0.5)),
+            ColliderType::Slope22UpLeftLower => Some((0.5, 0.)),
+            _ => None,
+        }
+    }
+
+    /// Whether the given edge of a cell of this type is completely filled, which
+    /// means that the neighboring cell's edge touching it can never be collided with.
+    fn fills_edge(&self, side: Side) -> bool {
+        match self {
+            ColliderType::Empty | ColliderType::OneWay => false,
+            ColliderType::Solid => true,
+            _ => {
+                let (left, right) = self
+                    .slope_heights()
+                    .expect("All other collider types should be slopes");
+                match side {
+                    Side::Top => false,
+                    Side::Bottom => true,
+                    Side::Left => left == 1.,
+                    Side::Right => right == 1.,
+                }
+            }
+        }
+    }
+
+    /// Whether something occupying the given area would collide with a cell of
+    /// this type occupying the given cell rect. One-way platforms can always be
+    /// passed through, and only the part of a slope below its surface counts.
+    fn occupies(&self, cell: &Rect, area: &Rect) -> bool {
+        if !cell.overlaps(area) {
+            return false;
+        }
+        match self {
+            ColliderType::Empty | ColliderType::OneWay => false,
+            ColliderType::Solid => true,
+            _ => {
+                let (left, right) = self
+                    .slope_heights()
+                    .expect("All other collider types should be slopes");
+                let shape = ColliderShape::Slope { left, right };
+                // The slope's surface is a straight line, so its highest point
+                // under the area is at one of the area's edges.
+                let highest_top = shape
+                    .top_at(cell, area.left())
+                    .min(shape.top_at(cell, area.right()));
+                area.bottom() > highest_top
+            }
+        }
+    }
 }
 
 impl TryFrom<i64> for ColliderType {
@@ -54,6 +138,13 @@ impl TryFrom<i64> for ColliderType {
         match value {
             0 => Ok(ColliderType::Empty),
             1 => Ok(ColliderType::Solid),
+            2 => Ok(ColliderType::OneWay),
+            3 => Ok(ColliderType::Slope45UpRight),
+            4 => Ok(ColliderType::Slope45UpLeft),
+            5 => Ok(ColliderType::Slope22UpRightLower),
+            6 => Ok(ColliderType::Slope22UpRightUpper),
+            7 => Ok(ColliderType::Slope22UpLeftUpper),
+            8 => Ok(ColliderType::Slope22UpLeftLower),
             _ => Err(anyhow!("Unknown IntGrid value: {}", value)),
         }
     }
@@ -95,8 +186,12 @@ pub struct Level {
     /// in LDtk.
     pub entity_tiles: Vec<Option<Tile>>,
 
-    /// Various other entities in the level.
-    pub entities: HashMap<Uuid, Entity>,
+    /// Various other entities in the level, ordered by their iids so that
+    /// they're always spawned in the same order.
+    pub entities: BTreeMap<Uuid, Entity>,
+
+    /// The texture that all the level's tiles come from.
+    tileset: Texture2D,
 }
 
 #[derive(Copy, Clone)]
@@ -143,12 +238,13 @@ pub enum EntityKind {
     Spear,
     Gem,
     Trigger(TriggerArgs),
+    Checkpoint,
 }
 
 impl Level {
-    pub fn from_ldtk(level: ldtk::Level) -> Result<Self> {
+    pub fn from_ldtk(level: ldtk::Level, ctx: &GameContext) -> Result<Self> {
         let mut colliders: Option<Vec<ColliderType>> = None;
-        let scale = config().sprite_scale;
+        let scale = ctx.config.sprite_scale;
         let world_rect = Rect::new(
             level.world_x as f32 * scale,
             level.world_y as f32 * scale,
@@ -156,7 +252,7 @@ impl Level {
             level.px_hei as f32 * scale,
         );
         let layers = level.layer_instances.unwrap();
-        let mut entities = HashMap::new();
+        let mut entities = BTreeMap::new();
         let mut opt_tiles: Option<Vec<Option<Tile>>> = None;
         let mut opt_background_tiles: Option<Vec<Option<Tile>>> = None;
         let mut opt_entity_tiles: Option<Vec<Option<Tile>>> = None;
@@ -210,8 +306,9 @@ impl Level {
                         }
                         "Trigger" => EntityKind::Trigger(TriggerArgs {
                             destroy_on_enter: field_into(&mut fields, "destroy_on_enter")?,
-                            play_sound_effect: get_sound_effect_info(&mut fields)?,
+                            play_sound_effect: get_sound_effect_info(&mut fields, ctx)?,
                         }),
+                        "Checkpoint" => EntityKind::Checkpoint,
                         _ => {
                             eprintln!("Unexpected entity found: {}", entity.identifier);
                             continue;
@@ -244,6 +341,7 @@ impl Level {
                 .ok_or(anyhow!("Couldn't find background tiles"))?,
             entity_tiles: opt_entity_tiles.ok_or(anyhow!("Couldn't find entity titles"))?,
             entities,
+            tileset: ctx.assets.tileset,
         })
     }
 
@@ -309,14 +407,14 @@ impl Level {
     }
 
     pub fn draw_entity_tiles(&self, bounding_rect: &Rect, point: &Vec2) {
-        let tileset = game_assets().tileset;
+        let tileset = self.tileset;
         // We're using floor() here to avoid weird visual artifacts between tiles.
         let final_point = (*point).floor() - bounding_rect.point();
         self.draw_tiles(&self.entity_tiles, tileset, &bounding_rect, &final_point);
     }
 
     pub fn draw(&self, bounding_rect: &Rect) {
-        let tileset = game_assets().tileset;
+        let tileset = self.tileset;
         self.draw_tiles(&self.background_tiles, tileset, &bounding_rect, &Vec2::ZERO);
         self.draw_tiles(&self.tiles, tileset, &bounding_rect, &Vec2::ZERO);
     }
@@ -341,11 +439,15 @@ impl Level {
         }
     }
 
-    fn is_occupied_at(&self, x: i64, y: i64) -> bool {
+    fn get_collider_type_at(&self, x: i64, y: i64) -> &ColliderType {
         if self.is_grid_coordinate_outside_of_bounds(x, y) {
-            return false;
+            return &ColliderType::Empty;
         }
-        self.colliders[self.get_index(x, y)] != ColliderType::Empty
+        &self.colliders[self.get_index(x, y)]
+    }
+
+    fn fills_edge_at(&self, x: i64, y: i64, side: Side) -> bool {
+        self.get_collider_type_at(x, y).fills_edge(side)
     }
 
     fn get_bounding_cell_rect_in_grid(&self, rect: &Rect) -> Rect {
@@ -395,9 +497,19 @@ impl Level {
         }
     }
 
+    /// Whether something could occupy the given area without colliding with
+    /// the level's geometry.
     pub fn is_area_vacant(&self, bounding_rect: &Rect) -> bool {
-        for collider in self.iter_colliders(bounding_rect) {
-            if collider.rect.overlaps(bounding_rect) {
+        let extents = self.get_bounding_cell_rect_in_grid(bounding_rect);
+        for (x, y) in XYRangeIterator::from(extents) {
+            let cell = Rect::new(
+                x as f32 * self.grid_size,
+                y as f32 * self.grid_size,
+                self.grid_size,
+                self.grid_size,
+            );
+            let collider_type = self.get_collider_type_at(x, y);
+            if collider_type.occupies(&cell, bounding_rect) {
                 return false;
             }
         }
@@ -417,10 +529,11 @@ impl Level {
 
 fn get_sound_effect_info(
     fields: &mut HashMap<String, FieldInstance>,
+    ctx: &GameContext,
 ) -> Result<Option<(SoundEffect, f32)>> {
     if let Some(play_sound_effect) = field_into::<Option<String>>(fields, "play_sound_effect")? {
         let volume: f32 = field_into(fields, "sound_effect_volume")?;
-        let assets = game_assets();
+        let assets = &ctx.assets;
         match play_sound_effect.as_str() {
             "DittyMusic" => Ok(Some((assets.music, volume))),
             "FoundSecret" => Ok(Some((assets.found_secret_sound, volume))),
@@ -509,31 +622,179 @@ impl<'a> Iterator for GridColliderIterator<'a> {
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             if let Some((x, y)) = self.range.next() {
-                if self.level.is_occupied_at(x, y) {
-                    let rect = Rect::new(
-                        x as f32 * self.level.grid_size,
-                        y as f32 * self.level.grid_size,
-                        self.level.grid_size,
-                        self.level.grid_size,
-                    );
-                    let collider = Collider {
-                        enable_top: !self.level.is_occupied_at(x, y - 1),
-                        enable_bottom: !self.level.is_occupied_at(x, y + 1),
-                        enable_left: !self.level.is_occupied_at(x - 1, y),
-                        enable_right: !self.level.is_occupied_at(x + 1, y),
-                        flags: CollisionFlags::ENVIRONMENT,
-                        rect,
-                        entity_id: None,
-                        prev_rect: rect,
-                        velocity: Vec2::ZERO,
-                    };
-                    return Some(collider);
-                } else {
+                let level = self.level;
+                let collider_type = level.get_collider_type_at(x, y);
+                if *collider_type == ColliderType::Empty {
                     continue;
                 }
+                let rect = Rect::new(
+                    x as f32 * level.grid_size,
+                    y as f32 * level.grid_size,
+                    level.grid_size,
+                    level.grid_size,
+                );
+                let mut collider = Collider {
+                    enable_top: !level.fills_edge_at(x, y - 1, Side::Bottom),
+                    enable_bottom: !level.fills_edge_at(x, y + 1, Side::Top),
+                    enable_left: !level.fills_edge_at(x - 1, y, Side::Right),
+                    enable_right: !level.fills_edge_at(x + 1, y, Side::Left),
+                    flags: CollisionFlags::ENVIRONMENT,
+                    rect,
+                    entity_id: None,
+                    prev_rect: rect,
+                    velocity: Vec2::ZERO,
+                    shape: ColliderShape::Flat,
+                };
+                if *collider_type == ColliderType::OneWay {
+                    collider.enable_bottom = false;
+                    collider.enable_left = false;
+                    collider.enable_right = false;
+                } else if let Some((left, right)) = collider_type.slope_heights() {
+                    // Even if there's something directly above us, the slope can
+                    // still be reached from its lower end.
+                    collider.enable_top = true;
+                    // Only the higher end of the slope has a wall.
+                    collider.enable_left &= left > right;
+                    collider.enable_right &= right > left;
+                    collider.shape = ColliderShape::Slope { left, right };
+                }
+                return Some(collider);
             } else {
                 return None;
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use macroquad::prelude::{Rect, Texture2D};
+
+    use crate::collision::Collider;
+
+    use super::{ColliderType, Level};
+
+    const GRID_SIZE: f32 = 10.;
+
+    /// Make a level whose IntGrid layer consists of the given rows of values.
+    fn make_level(rows: &[&[i64]]) -> Level {
+        let width = rows[0].len() as i64;
+        let height = rows.len() as i64;
+        let values = rows.concat();
+        let num_cells = values.len();
+        Level {
+            identifier: "Test_Level".to_owned(),
+            width,
+            height,
+            grid_size: GRID_SIZE,
+            unscaled_grid_size: GRID_SIZE as i64,
+            world_rect: Rect::new(0., 0., width as f32 * GRID_SIZE, height as f32 * GRID_SIZE),
+            colliders: ColliderType::from_vec(&values).unwrap(),
+            tiles: vec![None; num_cells],
+            background_tiles: vec![None; num_cells],
+            entity_tiles: vec![None; num_cells],
+            entities: BTreeMap::new(),
+            tileset: Texture2D::empty(),
+        }
+    }
+
+    fn cell_rect(x: i64, y: i64) -> Rect {
+        Rect::new(
+            x as f32 * GRID_SIZE,
+            y as f32 * GRID_SIZE,
+            GRID_SIZE,
+            GRID_SIZE,
+        )
+    }
+
+    fn collider_at(level: &Level, x: i64, y: i64) -> Collider {
+        let rect = cell_rect(x, y);
+        level
+            .iter_colliders(&rect)
+            .find(|collider| collider.rect == rect)
+            .expect("Cell should have a collider")
+    }
+
+    /// Returns whether the top, bottom, left, and right sides of the collider
+    /// are enabled, in that order.
+    fn enabled_sides(collider: &Collider) -> (bool, bool, bool, bool) {
+        (
+            collider.enable_top,
+            collider.enable_bottom,
+            collider.enable_left,
+            collider.enable_right,
+        )
+    }
+
+    #[test]
+    fn test_one_way_platforms_only_collide_on_top() {
+        let level = make_level(&[&[0, 0, 0], &[1, 2, 1], &[0, 1, 0]]);
+        assert_eq!(
+            enabled_sides(&collider_at(&level, 1, 1)),
+            (true, false, false, false)
+        );
+        // One-way platforms don't hide the edges of their neighbors.
+        assert_eq!(
+            enabled_sides(&collider_at(&level, 0, 1)),
+            (true, true, true, true)
+        );
+        assert_eq!(
+            enabled_sides(&collider_at(&level, 1, 2)),
+            (true, true, true, true)
+        );
+    }
+
+    #[test]
+    fn test_slopes_only_have_walls_at_their_higher_ends() {
+        let level = make_level(&[&[0, 1, 1, 0], &[0, 3, 4, 0], &[1, 1, 1, 1]]);
+        // Both slopes can be landed on even though there's something above
+        // them, and they hide each other's walls where they meet.
+        assert_eq!(
+            enabled_sides(&collider_at(&level, 1, 1)),
+            (true, false, false, false)
+        );
+        assert_eq!(
+            enabled_sides(&collider_at(&level, 2, 1)),
+            (true, false, false, false)
+        );
+        // The bottoms of slopes are filled, so they hide the tops of what's
+        // below them.
+        assert!(!collider_at(&level, 1, 2).enable_top);
+
+        let level = make_level(&[&[3, 0, 8]]);
+        assert_eq!(
+            enabled_sides(&collider_at(&level, 0, 0)),
+            (true, true, false, true)
+        );
+        assert_eq!(
+            enabled_sides(&collider_at(&level, 2, 0)),
+            (true, true, true, false)
+        );
+    }
+
+    #[test]
+    fn test_slopes_hide_edges_of_neighbors_only_at_their_higher_ends() {
+        let level = make_level(&[&[1, 3, 1]]);
+        assert!(collider_at(&level, 0, 0).enable_right);
+        assert!(!collider_at(&level, 2, 0).enable_left);
+
+        let level = make_level(&[&[1, 6, 1]]);
+        assert!(collider_at(&level, 0, 0).enable_right);
+        assert!(!collider_at(&level, 2, 0).enable_left);
+    }
+
+    #[test]
+    fn test_areas_are_vacant_above_slopes_and_around_one_way_platforms() {
+        let level = make_level(&[&[0, 0, 0], &[3, 2, 1]]);
+        // In the empty part of the slope's cell, above its low end.
+        assert!(level.is_area_vacant(&Rect::new(0., 12., 3., 4.)));
+        // Under the slope's surface.
+        assert!(!level.is_area_vacant(&Rect::new(5., 12., 4., 4.)));
+        // Passing through the one-way platform.
+        assert!(level.is_area_vacant(&Rect::new(12., 5., 4., 10.)));
+        // Overlapping the solid cell.
+        assert!(!level.is_area_vacant(&Rect::new(22., 5., 4., 10.)));
+    }
+}
@@ -1,12 +1,16 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Write;
 use std::rc::Rc;
 
-use crate::attachment::attachment_system;
+use crate::attachment::{attachment_system, push_attachment_events};
+use crate::checkpoint::{checkpoint_system, create_checkpoint};
+use crate::checksum::checksum_entities;
+use crate::context::GameContext;
 use crate::crate_entity::create_crate;
 use crate::drawing::draw_rect_lines;
 use crate::dynamic_collider::DynamicColliderSystem;
-use crate::entity::{Entity, EntityMap};
+use crate::entity::{Entity, EntityMap, MAIN_PLAYER_ENTITY_ID};
+use crate::events::GameEvent;
 use crate::floor_switch::{create_floor_switch, floor_switch_system};
 use crate::flying_eye::{create_flying_eye, flying_eye_movement_system};
 use crate::foreground_tiles::create_foreground_tiles;
@@ -19,11 +23,12 @@ use crate::mushroom::{create_mushrom, mushroom_movement_system};
 use crate::physics::{physics_system_resolve_collisions, physics_system_update_positions};
 use crate::pickups::{create_gem, create_spear, pickup_system};
 use crate::player::{
-    did_fall_off_level, player_update_system, process_player_input, should_switch_levels,
-    teleport_entity,
+    create_player, did_fall_off_level, player_update_system, process_player_input,
+    should_switch_levels, teleport_entity,
 };
 use crate::push::push_system;
 use crate::route::{draw_route_debug_targets, route_system};
+use crate::spatial_index::SpatialIndex;
 use crate::steering::steering_system;
 use crate::switch::switch_system;
 use crate::text::{create_text_entity, draw_level_text, update_level_text};
@@ -32,8 +37,8 @@ use crate::trigger::create_trigger;
 use crate::world::World;
 use crate::z_index::ZIndexedDrawingSystem;
 use crate::{camera::Camera, level::EntityKind};
-use anyhow::Result;
-use macroquad::prelude::{PURPLE, WHITE};
+use anyhow::{anyhow, Result};
+use macroquad::prelude::{Vec2, PURPLE, WHITE};
 use uuid::Uuid;
 
 use crate::level::Level;
@@ -46,13 +51,28 @@ pub enum FrameResult {
     MainPlayerDied,
 }
 
-#[derive(Clone)]
+/// A snapshot of a `LevelRuntime`'s state, which can be serialized.
+///
+/// Nothing in here is stored in a hash map, so the same state always
+/// serializes to the same output.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SavedLevelRuntime {
-    level: Rc<Level>,
-    world: Rc<World>,
+    /// The identifier of the level that the main player is in.
+    level: String,
     entities: EntityMap,
     camera: Camera,
     dynamic_collider_system: DynamicColliderSystem,
+    checkpoint: Option<Rc<SavedLevelRuntime>>,
+    level_states: BTreeMap<String, Rc<LevelState>>,
+}
+
+/// The state of a level's entities at the time the main player left it, so that
+/// it can be restored if they come back.
+#[derive(Serialize, Deserialize)]
+struct LevelState {
+    /// The level's entities, keyed by their LDtk iids, along with their runtime ids.
+    /// Entities that were destroyed (e.g. collected pickups) won't be in here.
+    entities: BTreeMap<Uuid, (u64, Entity)>,
 }
 
 pub struct LevelRuntime {
@@ -62,54 +82,175 @@ pub struct LevelRuntime {
     camera: Camera,
     dynamic_collider_system: DynamicColliderSystem,
     z_indexed_drawing_system: ZIndexedDrawingSystem,
+    /// An index of the entities' bounding boxes. It's rebuilt at the beginning of
+    /// every frame, and the systems that move entities keep it up to date.
+    spatial_index: SpatialIndex,
+    /// The events that happened during the most recent frame.
+    events: Vec<GameEvent>,
+    /// The state of the game when the main player last reached a checkpoint.
+    checkpoint: Option<Rc<SavedLevelRuntime>>,
+    /// The state of every level the main player has left, keyed by the level's
+    /// identifier.
+    level_states: BTreeMap<String, Rc<LevelState>>,
 }
 
 impl LevelRuntime {
     pub fn new(player: Entity, level: Rc<Level>, world: Rc<World>) -> Self {
-        let mut instance = Self::from_saved(SavedLevelRuntime {
+        let camera = Camera::new(&world.ctx().config);
+        let mut instance = LevelRuntime {
             level: level.clone(),
             world,
             entities: EntityMap::new_ex(player, ENTITY_CAPACITY),
-            camera: Camera::new(),
-            dynamic_collider_system: DynamicColliderSystem::with_capacity(ENTITY_CAPACITY),
-        });
-        instance.change_level(level);
+            camera,
+            dynamic_collider_system: DynamicColliderSystem::default(),
+            z_indexed_drawing_system: ZIndexedDrawingSystem::with_capacity(ENTITY_CAPACITY),
+            spatial_index: SpatialIndex::default(),
+            events: vec![],
+            checkpoint: None,
+            level_states: BTreeMap::new(),
+        };
+        instance.spawn_entities();
         instance
     }
 
-    pub fn from_saved(saved: SavedLevelRuntime) -> Self {
-        LevelRuntime {
-            level: saved.level,
-            world: saved.world,
+    /// Restore a saved level runtime. This fails if the saved level runtime's level
+    /// doesn't exist in the given world, which can happen if it was saved with a
+    /// different version of the world.
+    pub fn from_saved(saved: SavedLevelRuntime, world: Rc<World>) -> Result<Self> {
+        let Some(level) = world.level(&saved.level) else {
+            return Err(anyhow!("Level '{}' does not exist", saved.level));
+        };
+        Ok(LevelRuntime {
+            level,
+            world,
             entities: saved.entities,
             camera: saved.camera,
             dynamic_collider_system: saved.dynamic_collider_system,
             z_indexed_drawing_system: ZIndexedDrawingSystem::with_capacity(ENTITY_CAPACITY),
-        }
+            spatial_index: SpatialIndex::default(),
+            events: vec![],
+            checkpoint: saved.checkpoint,
+            level_states: saved.level_states,
+        })
     }
 
     pub fn save(&self) -> SavedLevelRuntime {
         SavedLevelRuntime {
-            level: self.level.clone(),
-            world: self.world.clone(),
+            checkpoint: self.checkpoint.clone(),
+            ..self.save_without_checkpoint()
+        }
+    }
+
+    fn save_without_checkpoint(&self) -> SavedLevelRuntime {
+        SavedLevelRuntime {
+            level: self.level.identifier.clone(),
             entities: self.entities.clone(),
             camera: self.camera,
             dynamic_collider_system: self.dynamic_collider_system.clone(),
+            checkpoint: None,
+            level_states: self.level_states.clone(),
         }
     }
 
+    /// Create a new level runtime for the given world, which is a newer version
+    /// of this runtime's world that was just reloaded from disk.
+    ///
+    /// The main player stays where they are in the current level, but everything
+    /// else in it is respawned from the new world. Checkpoints and the states of
+    /// other levels are discarded, since they may refer to entities that no
+    /// longer exist.
+    pub fn reload(&self, world: Rc<World>) -> Result<LevelRuntime> {
+        let Some(level) = world.level(&self.level.identifier) else {
+            return Err(anyhow!("Level '{}' does not exist", self.level.identifier));
+        };
+        let mut player = *self.entities.main_player();
+        let pos = player.sprite.pos;
+        // Whatever the player was attached to is about to be respawned.
+        teleport_entity(&mut player, pos);
+        let mut runtime = LevelRuntime::new(player, level, world);
+        runtime.camera = self.camera;
+        Ok(runtime)
+    }
+
+    /// Create a new level runtime that starts from the last checkpoint the main
+    /// player reached, if any. This is how the player respawns after dying.
+    pub fn respawn_at_checkpoint(&self) -> Option<LevelRuntime> {
+        let checkpoint = self.checkpoint.as_ref()?;
+        let mut runtime = LevelRuntime::from_saved((**checkpoint).clone(), self.world.clone())
+            .expect("Checkpoints are always from the current world");
+        runtime.checkpoint = Some(checkpoint.clone());
+        // The checkpoint may have been reached mid-jump, mid-fall or while riding
+        // something, so start the player from a standstill, or they could end up
+        // falling to their death over and over again.
+        let player = runtime.entities.main_player_mut();
+        let pos = player.sprite.pos;
+        teleport_entity(player, pos);
+        player.physics.velocity = Vec2::ZERO;
+        player.physics.x_impulse = 0.;
+        Some(runtime)
+    }
+
+    pub fn ctx(&self) -> &GameContext {
+        self.world.ctx()
+    }
+
+    /// The level that the main player is currently in.
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
+
+    /// The events that happened during the most recent call to
+    /// `advance_one_frame()`, in the order they happened.
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    /// All the entities in the current level, including the main player.
+    pub fn entities(&self) -> &EntityMap {
+        &self.entities
+    }
+
+    /// A checksum of the current simulation state, used to detect whether a
+    /// replay of a recording has diverged from the original.
+    pub fn checksum(&self) -> u64 {
+        checksum_entities(&self.level.identifier, &self.entities)
+    }
+
     fn change_level(&mut self, level: Rc<Level>) {
+        let mut state = LevelState {
+            entities: BTreeMap::new(),
+        };
+        for (id, entity) in self.entities.remove_all_except_main_player() {
+            if let Some(iid) = entity.iid {
+                state.entities.insert(iid, (id, entity));
+            } else {
+                self.entities.remove(id);
+            }
+        }
+        self.level_states
+            .insert(self.level.identifier.clone(), Rc::new(state));
         self.level = level;
-        self.entities.clear_all_except_main_player();
         self.spawn_entities();
     }
 
     fn spawn_entities(&mut self) {
+        if let Some(state) = self.level_states.remove(&self.level.identifier) {
+            // The main player has been here before, so put everything back the way
+            // it was. The entities' old ids were kept reserved while the main player
+            // was away, so any references between them are still valid.
+            for &(id, entity) in state.entities.values() {
+                self.entities.insert(id, entity);
+            }
+            return;
+        }
+
         // Create a mapping from LDtk Entity IIDs to our runtime entity IDs. We'll do this
         // up-front so we can convert EntityRefs in our Entities into entity IDs at spawn time,
         // rather than having to do it every frame.
         let mut iid_id_map: HashMap<Uuid, u64> = HashMap::with_capacity(self.level.entities.len());
         let level = self.level.clone();
+        let world = self.world.clone();
+        let ctx = world.ctx();
         for entity in level.entities.values() {
             let result = iid_id_map.insert(entity.iid, self.entities.new_id());
             assert!(
@@ -120,16 +261,21 @@ impl LevelRuntime {
 
         for entity in level.entities.values() {
             let opt_instance = match &entity.kind {
-                EntityKind::FlyingEye(velocity) => Some(create_flying_eye(entity.rect, *velocity)),
-                EntityKind::Spear => Some(create_spear(entity.rect)),
-                EntityKind::Gem => Some(create_gem(entity.rect)),
-                EntityKind::Mushroom => Some(create_mushrom(entity.rect)),
-                EntityKind::MovingPlatform(args) => Some(create_moving_platform(entity.rect, args)),
+                EntityKind::FlyingEye(velocity) => {
+                    Some(create_flying_eye(entity.rect, *velocity, ctx))
+                }
+                EntityKind::Spear => Some(create_spear(entity.rect, ctx)),
+                EntityKind::Gem => Some(create_gem(entity.rect, ctx)),
+                EntityKind::Mushroom => Some(create_mushrom(entity.rect, ctx)),
+                EntityKind::MovingPlatform(args) => {
+                    Some(create_moving_platform(entity.rect, args, ctx))
+                }
                 EntityKind::ForegroundTiles => Some(create_foreground_tiles(entity.rect)),
-                EntityKind::Crate => Some(create_crate(entity.rect)),
+                EntityKind::Crate => Some(create_crate(entity.rect, ctx)),
                 EntityKind::FloorSwitch(trigger_entity_iid) => Some(create_floor_switch(
                     entity.rect,
                     trigger_entity_iid.as_ref().map(|s| iid_id_map[&s.iid]),
+                    ctx,
                 )),
                 EntityKind::Trigger(args) => Some(create_trigger(
                     entity.rect,
@@ -137,10 +283,14 @@ impl LevelRuntime {
                 )),
                 EntityKind::PlayerStart(..) => None,
                 EntityKind::Text(..) => Some(create_text_entity(entity.rect)),
+                EntityKind::Checkpoint => Some(create_checkpoint(entity.rect)),
             };
+            let id = iid_id_map[&entity.iid];
             if let Some(mut instance) = opt_instance {
                 instance.iid = Some(entity.iid);
-                self.entities.insert(iid_id_map[&entity.iid], instance);
+                self.entities.insert(id, instance);
+            } else {
+                self.entities.remove(id);
             }
         }
     }
@@ -150,7 +300,18 @@ impl LevelRuntime {
         if let Some((new_level, new_pos)) =
             should_switch_levels(&player.sprite, &self.level, &self.world)
         {
+            let attachment = player.attachment.unwrap();
             teleport_entity(player, new_pos);
+            push_attachment_events(
+                MAIN_PLAYER_ENTITY_ID,
+                attachment.attached_to_entity_id(),
+                player.attachment.as_ref().unwrap(),
+                &mut self.events,
+            );
+            self.events.push(GameEvent::LevelChanged {
+                from: self.level.identifier.clone(),
+                to: new_level.identifier.clone(),
+            });
             self.change_level(new_level);
             true
         } else {
@@ -159,49 +320,74 @@ impl LevelRuntime {
     }
 
     pub fn advance_one_frame(&mut self, time: &GameTime, input: &InputState) -> FrameResult {
+        self.events.clear();
         if !self.maybe_switch_level()
-            && did_fall_off_level(&self.entities.main_player().sprite, &self.level)
+            && did_fall_off_level(
+                &self.entities.main_player().sprite,
+                &self.level,
+                self.world.ctx(),
+            )
         {
             return FrameResult::MainPlayerDied;
         }
 
-        process_player_input(&mut self.entities, time, input);
+        let ctx = self.world.ctx();
+        let events = &mut self.events;
+        process_player_input(&mut self.entities, events, time, input, ctx);
         steering_system(&mut self.entities);
-        attachment_system(&mut self.entities, &self.level, time);
-        route_system(&mut self.entities);
-        physics_system_update_positions(&mut self.entities, time);
+        attachment_system(&mut self.entities, events, &self.level, time, ctx);
+        self.spatial_index.rebuild(&self.entities);
+        route_system(&mut self.entities, &mut self.spatial_index, ctx);
+        physics_system_update_positions(&mut self.entities, &mut self.spatial_index, time, ctx);
         self.dynamic_collider_system.run(&mut self.entities);
-        push_system(&mut self.entities);
-        switch_system(&mut self.entities, &self.level);
+        push_system(&mut self.entities, &mut self.spatial_index);
+        switch_system(
+            &mut self.entities,
+            &mut self.spatial_index,
+            events,
+            &self.level,
+        );
         physics_system_resolve_collisions(
             &mut self.entities,
+            &mut self.spatial_index,
             &self.level,
             &mut self.dynamic_collider_system,
+            ctx,
         );
         child_component_system(&mut self.entities);
         floor_switch_system(&mut self.entities);
         flying_eye_movement_system(&mut self.entities, time);
-        mushroom_movement_system(&mut self.entities, time);
-        life_transfer_system(&mut self.entities, time);
-        pickup_system(&mut self.entities, time);
-        player_update_system(&mut self.entities, time);
+        mushroom_movement_system(&mut self.entities, events, time, ctx);
+        life_transfer_system(&mut self.entities, time, ctx);
+        pickup_system(
+            &mut self.entities,
+            &mut self.spatial_index,
+            events,
+            time,
+            ctx,
+        );
+        player_update_system(&mut self.entities, events, time, ctx);
+        if checkpoint_system(&mut self.entities) {
+            self.checkpoint = Some(Rc::new(self.save_without_checkpoint()));
+        }
 
         self.camera
             .update(&self.entities.main_player(), &self.level);
-        update_level_text(&mut self.entities, time);
+        update_level_text(&mut self.entities, time, ctx);
 
         return FrameResult::Ok;
     }
 
     pub fn draw(&self) {
+        let ctx = self.world.ctx();
         self.camera.with_active(|| {
             self.level.draw(&self.camera.rect());
             self.z_indexed_drawing_system
-                .draw_entities(&self.entities, &self.level);
+                .draw_entities(&self.entities, &self.level, ctx);
         });
 
-        draw_level_text(&self.entities, &self.level);
-        draw_gem_counter(&self.entities, &self.level);
+        draw_level_text(&self.entities, &self.level, ctx);
+        draw_gem_counter(&self.entities, &self.level, &self.world);
     }
 
     pub fn generate_debug_text(&self, text: &mut String) -> Result<()> {
@@ -228,7 +414,7 @@ impl LevelRuntime {
                 collider.draw_debug_rect(PURPLE);
             }
             self.dynamic_collider_system.draw_debug_rects();
-            draw_route_debug_targets(&self.entities);
+            draw_route_debug_targets(&self.entities, self.ctx());
             draw_rect_lines(
                 &level.get_bounding_cell_rect(&self.entities.main_player().sprite.bbox()),
                 1.,
@@ -241,3 +427,14 @@ impl LevelRuntime {
         });
     }
 }
+
+/// Start a new game with the main player at the PlayerStart entity with the
+/// given name.
+pub fn new_game(start_position: &str, world: Rc<World>) -> LevelRuntime {
+    let (level_start, player_start) = world.player_start(start_position).expect(&format!(
+        "World does not define a PlayerStart entity called '{}'!",
+        start_position
+    ));
+    let player = create_player(player_start, "PLAYER", world.ctx());
+    LevelRuntime::new(player, level_start, world)
+}
@@ -0,0 +1,101 @@
+//! The simulation (and rendering) code for macroquad-fun.
+//!
+//! The game's executable is a thin shell around this library. The library
+//! can also be used to run the game's simulation without a window, which is
+//! useful for things like level validators, replay analyzers, and bots:
+//!
+//! ```no_run
+//! use macroquad_fun::{headless::load_headless, input::Buttons, simulation::Simulation};
+//!
+//! let world = load_headless(macroquad_fun::CONFIG_PATH, macroquad_fun::WORLD_PATH).unwrap();
+//! let mut simulation = Simulation::new("default", world);
+//! for _ in 0..60 {
+//!     simulation.advance_one_frame(Buttons::RIGHT);
+//! }
+//! let runtime = simulation.level_runtime();
+//! for (id, entity) in runtime.entities().iter() {
+//!     println!("{} {} is at {}", id, entity, entity.sprite.pos);
+//! }
+//! ```
+//!
+//! Good entrypoints for understanding the architecture can be found in
+//! [`level_runtime`] and [`entity`].
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+mod animator;
+mod aseprite;
+pub mod asset_loader;
+mod attachment;
+mod audio;
+mod camera;
+mod checkpoint;
+pub mod checksum;
+pub mod cli;
+mod collision;
+pub mod config;
+pub mod context;
+mod crate_entity;
+pub mod debug_mode;
+mod drawing;
+mod dynamic_collider;
+pub mod entity;
+pub mod events;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod file_watcher;
+mod floor_switch;
+mod flying_eye;
+mod font;
+mod foreground_tiles;
+pub mod fps;
+pub mod game_assets;
+mod gamepad;
+mod gem_counter;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
+mod hierarchy;
+pub mod input;
+pub mod js_interop;
+mod ldtk;
+pub mod level;
+pub mod level_runtime;
+mod life_transfer;
+mod materials;
+mod math_util;
+mod moving_platform;
+mod mushroom;
+pub mod outcomes;
+mod physics;
+mod pickups;
+mod player;
+mod push;
+pub mod recorder;
+pub mod rewind;
+mod route;
+mod running;
+pub mod save_slots;
+pub mod scene;
+mod serde_util;
+pub mod simulation;
+mod spatial_index;
+mod sprite_component;
+mod sprite_renderer;
+mod steering;
+mod switch;
+mod text;
+pub mod time;
+pub mod time_stream;
+pub mod touch_controls;
+mod trigger;
+pub mod world;
+mod xy_range_iterator;
+mod z_index;
+
+/// Where the game's configuration lives, relative to the root of the repository.
+pub const CONFIG_PATH: &str = "media/config.json";
+
+/// Where the game's LDtk world lives, relative to the root of the repository.
+pub const WORLD_PATH: &str = "media/world.ldtk";
@@ -1,12 +1,13 @@
 use macroquad::prelude::clamp;
 
 use crate::{
-    config::config,
+    config::Config,
+    context::GameContext,
     entity::{filter_and_process_entities, EntityMap},
     time::GameTime,
 };
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum LifeTransfer {
     Giving(f32),
     Receiving(f32),
@@ -14,7 +15,7 @@ pub enum LifeTransfer {
 
 pub type LifeTransferComponent = LifeTransfer;
 
-pub fn life_transfer_system(entities: &mut EntityMap, time: &GameTime) {
+pub fn life_transfer_system(entities: &mut EntityMap, time: &GameTime, ctx: &GameContext) {
     filter_and_process_entities(
         entities,
         |entity| matches!(entity.life_transfer, Some(LifeTransfer::Giving(_))),
@@ -36,18 +37,22 @@ pub fn life_transfer_system(entities: &mut EntityMap, time: &GameTime) {
                 }
                 did_give = true;
                 life_receiving_entity.life_transfer = Some(LifeTransfer::Receiving(
-                    recompute_transfer_amount(receive_amount, time, true),
+                    recompute_transfer_amount(receive_amount, time, true, &ctx.config),
                 ));
             }
             life_giving_entity.life_transfer = Some(LifeTransfer::Giving(
-                recompute_transfer_amount(give_amount, time, did_give),
+                recompute_transfer_amount(give_amount, time, did_give, &ctx.config),
             ));
         },
     );
 }
 
-fn recompute_transfer_amount(prev: f32, time: &GameTime, is_positive: bool) -> f32 {
-    let config = config();
+fn recompute_transfer_amount(
+    prev: f32,
+    time: &GameTime,
+    is_positive: bool,
+    config: &Config,
+) -> f32 {
     let mut delta = config.life_transfer_rate * time.time_since_last_frame as f32;
     if !is_positive {
         delta *= -1.;
@@ -1,82 +1,49 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-extern crate serde;
-#[macro_use]
-extern crate serde_derive;
-extern crate serde_json;
-
 use std::rc::Rc;
 
-use cli::Cli;
-use config::load_config;
-use debug_mode::DebugMode;
-use fps::FpsCounter;
-use game_assets::load_game_assets;
-use input::{InputState, InputStream};
-use level_runtime::{FrameResult, LevelRuntime, SavedLevelRuntime};
 use macroquad::prelude::*;
-use player::create_player;
-use time::FixedGameTime;
-use time_stream::{create_fixed_fps_time_stream, create_real_time_stream, TimeStream};
-use world::World;
-
-mod animator;
-mod aseprite;
-mod attachment;
-mod audio;
-mod camera;
-mod cli;
-mod collision;
-mod config;
-mod crate_entity;
-mod debug_mode;
-mod drawing;
-mod dynamic_collider;
-mod entity;
-mod floor_switch;
-mod flying_eye;
-mod font;
-mod foreground_tiles;
-mod fps;
-mod game_assets;
-mod gem_counter;
-mod hierarchy;
-mod input;
-mod js_interop;
-mod ldtk;
-mod level;
-mod level_runtime;
-mod life_transfer;
-mod materials;
-mod math_util;
-mod moving_platform;
-mod mushroom;
-mod physics;
-mod pickups;
-mod player;
-mod push;
-mod recorder;
-mod route;
-mod running;
-mod sprite_component;
-mod sprite_renderer;
-mod steering;
-mod switch;
-mod text;
-mod time;
-mod time_stream;
-mod trigger;
-mod world;
-mod xy_range_iterator;
-mod z_index;
+use macroquad_fun::{
+    asset_loader::AssetLoader,
+    checksum::{ChecksumChannel, SharedChecksumChannel},
+    cli::Cli,
+    context::GameContext,
+    debug_mode::DebugMode,
+    fps::FpsCounter,
+    input::{InputState, InputStream},
+    level_runtime::{LevelRuntime, SavedLevelRuntime},
+    recorder::RecordingMetadata,
+    rewind::RewindBuffer,
+    save_slots::{SaveSlot, SaveSlots},
+    scene::{Scene, SceneManager},
+    time::FixedGameTime,
+    time_stream::{create_fixed_fps_time_stream, create_real_time_stream, TimeStream},
+    touch_controls,
+    world::World,
+    CONFIG_PATH, WORLD_PATH,
+};
 
 #[cfg(target_arch = "wasm32")]
-use js_interop::js_interop_wasm32 as js;
-
-const CONFIG_PATH: &str = "media/config.json";
+use macroquad_fun::js_interop::js_interop_wasm32 as js;
+#[cfg(not(target_arch = "wasm32"))]
+use macroquad_fun::{config, file_watcher::FileWatcher};
 
 const EXPORT_FRAMES_FPS: u64 = 30;
 
+/// How far back in time the player can rewind.
+const REWIND_SECONDS: u64 = 10;
+
+/// How often we take a snapshot of the game state for rewinding, in fixed frames.
+const FRAMES_PER_REWIND_SNAPSHOT: u64 = 4;
+
+/// The keys that select which save slot F5 and F9 use, along with the slots' names.
+const SAVE_SLOT_KEYS: [(KeyCode, &str); 4] = [
+    (KeyCode::Key1, "1"),
+    (KeyCode::Key2, "2"),
+    (KeyCode::Key3, "3"),
+    (KeyCode::Key4, "4"),
+];
+
 fn window_conf() -> Conf {
     #[cfg(target_arch = "wasm32")]
     return Default::default();
@@ -106,17 +73,20 @@ fn window_conf() -> Conf {
 }
 
 #[cfg(target_arch = "wasm32")]
-fn create_input_stream(_args: &Cli) -> InputStream {
-    js::create_input_stream()
+fn create_input_stream(args: &Cli, world: &World, checksums: SharedChecksumChannel) -> InputStream {
+    let metadata = RecordingMetadata::current(&args.start_position, world);
+    js::create_input_stream(&metadata, checksums, &world.ctx().config.key_bindings)
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-fn create_input_stream(args: &Cli) -> InputStream {
-    use crate::recorder::InputPlayer;
-    use input::create_macroquad_input_stream;
-    use recorder::InputRecorder;
+fn create_input_stream(args: &Cli, world: &World, checksums: SharedChecksumChannel) -> InputStream {
+    use macroquad_fun::input::create_macroquad_input_stream;
+    use macroquad_fun::recorder::{InputPlayer, InputRecorder};
     use std::{cell::RefCell, io::BufWriter};
 
+    let metadata = RecordingMetadata::current(&args.start_position, world);
+    let key_bindings = &world.ctx().config.key_bindings;
+
     if let Some(filename) = &args.record {
         println!("Writing recording to '{}'.", filename);
         // Ideally we should be keeping a reference to the output and flushing it explicitly
@@ -126,24 +96,50 @@ fn create_input_stream(args: &Cli) -> InputStream {
         let output = Rc::new(RefCell::new(BufWriter::new(
             std::fs::File::create(filename).expect("Unable to create recording file"),
         )));
-        InputRecorder::new(create_macroquad_input_stream(), output)
+        InputRecorder::new(
+            create_macroquad_input_stream(key_bindings),
+            output,
+            &metadata,
+            checksums,
+        )
     } else if let Some(filename) = &args.playback {
         println!("Playing back recording from '{}'.", filename);
         Box::new(
             InputPlayer::new(
                 std::fs::read(filename).expect("Unable to open recording file for reading"),
+                &metadata,
+                checksums,
             )
+            .expect("Unable to play back recording")
             .chain(if args.stop_at_end {
                 Box::new(std::iter::empty())
             } else {
-                create_macroquad_input_stream()
+                create_macroquad_input_stream(key_bindings)
             }),
         )
     } else {
-        create_macroquad_input_stream()
+        create_macroquad_input_stream(key_bindings)
     }
 }
 
+/// Reload the world from disk, along with the config if it changed. The game
+/// assets are reused, since only the config and world are hot-reloaded.
+#[cfg(not(target_arch = "wasm32"))]
+async fn reload_world(
+    args: &Cli,
+    loader: &AssetLoader,
+    world: &World,
+    reload_config: bool,
+) -> anyhow::Result<Rc<World>> {
+    let ctx = if reload_config {
+        let config = config::load_config(CONFIG_PATH, args, loader).await?;
+        world.ctx().with_config(config)
+    } else {
+        world.ctx().clone()
+    };
+    Ok(Rc::new(World::load(WORLD_PATH, ctx, loader).await?))
+}
+
 fn create_time_stream(args: &Cli) -> TimeStream {
     if args.export_frames.is_some() {
         create_fixed_fps_time_stream(EXPORT_FRAMES_FPS)
@@ -152,43 +148,41 @@ fn create_time_stream(args: &Cli) -> TimeStream {
     }
 }
 
-fn draw_pause_overlay(is_browser: bool) {
-    draw_rectangle(
-        0.,
-        0.,
-        screen_width(),
-        screen_height(),
-        color_u8!(0., 0., 0., 128.),
-    );
-    let font = &game_assets::game_assets().font;
-    let text = if is_browser {
-        "Game paused (press ESC to unpause)"
-    } else {
-        "Game paused (press Q to quit)"
-    };
-    font.draw_centered_text(text, screen_width() / 2., screen_height() / 2., WHITE);
+fn main() {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let args = Cli::get_for_platform();
+        if args.headless {
+            println!(
+                "Starting macroquad-fun v{} in headless mode.",
+                env!("CARGO_PKG_VERSION")
+            );
+            macroquad_fun::headless::run_headless(&args).expect("run_headless() must succeed");
+            return;
+        }
+    }
+
+    // This is what the `#[macroquad::main]` attribute normally expands to; we're
+    // doing it manually so that we can avoid opening a window in headless mode.
+    macroquad::Window::from_config(window_conf(), run_windowed());
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
+async fn run_windowed() {
     let args = Cli::get_for_platform();
 
     println!("Starting macroquad-fun v{}.", env!("CARGO_PKG_VERSION"));
 
-    load_config(CONFIG_PATH, &args)
+    let loader = AssetLoader::Macroquad;
+    let ctx = GameContext::load(CONFIG_PATH, &args, loader)
         .await
-        .expect("load_config() must succeed");
-    load_game_assets()
-        .await
-        .expect("load_game_sprites() must succeed");
+        .expect("GameContext::load() must succeed");
     let world = Rc::new(
-        World::load("media/world.ldtk")
+        World::load(WORLD_PATH, ctx.clone(), &loader)
             .await
             .expect("World::load() must succeed"),
     );
 
-    let mut level_runtime = new_game(&args.start_position, world.clone());
-    let config = config::config();
+    let config = &ctx.config;
 
     #[cfg(target_arch = "wasm32")]
     {
@@ -203,22 +197,83 @@ async fn main() {
         js::init();
     }
 
-    let mut fixed_time = FixedGameTime::new(config.fixed_fps, get_time());
+    let mut fixed_time = FixedGameTime::new(config, get_time());
     let mut enable_debug_mode = false;
     let mut opt_debug_mode: Option<DebugMode> = None;
     let mut render_fps = FpsCounter::default();
     let mut fixed_fps = FpsCounter::default();
     let mut input_state = InputState::default();
-    let mut input_stream = create_input_stream(&args);
-    let mut saved_state: Option<(SavedLevelRuntime, FixedGameTime)> = None;
+    let checksums = ChecksumChannel::new_shared(args.checksum_interval);
+    let mut input_stream = create_input_stream(&args, &world, checksums.clone());
+    let mut save_slots = SaveSlots::load().unwrap_or_else(|e| {
+        println!("Unable to load saved games: {}", e);
+        SaveSlots::default()
+    });
+    let mut current_save_slot = SAVE_SLOT_KEYS[0].1;
+    let mut rewind_buffer: RewindBuffer<(SavedLevelRuntime, FixedGameTime)> = RewindBuffer::new(
+        REWIND_SECONDS * config.fixed_fps,
+        FRAMES_PER_REWIND_SNAPSHOT,
+    );
+    let mut is_rewinding = false;
     let mut time_stream = create_time_stream(&args);
     let mut frame_number: u64 = 0;
     let is_browser = cfg!(target_arch = "wasm32");
+    // The browser always records the player's input, so its recordings can be
+    // replayed on the server.
+    let is_recording_or_playing_back =
+        is_browser || args.record.is_some() || args.playback.is_some();
+    // Recordings start as soon as the game does, so skip the title screen if
+    // we're playing one back.
+    let initial_scene = if args.playback.is_some() {
+        Scene::Playing
+    } else {
+        Scene::Title
+    };
+    let mut scenes = SceneManager::new(&args.start_position, world, initial_scene, get_time());
+    // Recordings can only be played back with the exact config and world they
+    // were made with, so we don't hot-reload while recording or playing back.
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut watchers = if !is_recording_or_playing_back {
+        Some([
+            FileWatcher::new(CONFIG_PATH, get_time()),
+            FileWatcher::new(WORLD_PATH, get_time()),
+        ])
+    } else {
+        None
+    };
 
     'outer: loop {
         let Some(now) = time_stream.next() else {
             break;
         };
+
+        scenes.update(now);
+        if scenes.scene() == Scene::Title {
+            // Don't let the player rewind into a previous game.
+            rewind_buffer.clear();
+        }
+
+        // We keep the game paused while rewinding, and step back through one
+        // snapshot per rendered frame. Recordings only contain the frames that
+        // were played forward, so we can't rewind while recording or playing back.
+        if !is_recording_or_playing_back
+            && is_key_down(KeyCode::R)
+            && (is_rewinding || scenes.is_simulating())
+        {
+            is_rewinding = true;
+            if let Some((saved_level_runtime, paused_time)) = rewind_buffer.rewind() {
+                scenes.set_level_runtime(
+                    LevelRuntime::from_saved(saved_level_runtime, scenes.world().clone())
+                        .expect("Rewind snapshots are always from the current world"),
+                );
+                fixed_time = paused_time;
+            }
+            input_state = InputState::default();
+        } else {
+            is_rewinding = false;
+        }
+        fixed_time.set_paused(is_rewinding || !scenes.is_simulating(), now);
+
         if !fixed_time.is_paused() {
             fixed_time.update(now);
 
@@ -228,17 +283,26 @@ async fn main() {
                 };
                 input_state.update(new_buttons);
                 fixed_fps.update(time.now);
-                match level_runtime.advance_one_frame(&time, &input_state) {
-                    FrameResult::Ok => {}
-                    FrameResult::MainPlayerDied => {
-                        level_runtime = new_game(&args.start_position, world.clone());
-                    }
+                scenes.advance_one_frame(&time, &input_state, now);
+                checksums
+                    .borrow_mut()
+                    .provide_if_requested(|| scenes.level_runtime().checksum());
+                if !scenes.is_simulating() {
+                    break;
                 }
             }
+            if !is_recording_or_playing_back {
+                rewind_buffer.maybe_record(fixed_time.frames_so_far(), || {
+                    (
+                        scenes.level_runtime().save(),
+                        fixed_time.create_paused_clone(),
+                    )
+                });
+            }
         }
 
         render_fps.update(now);
-        level_runtime.draw();
+        scenes.draw(now, is_browser);
 
         if let Some(basename) = &args.export_frames {
             let screen_data = get_screen_data();
@@ -248,60 +312,101 @@ async fn main() {
         }
 
         if is_key_released(KeyCode::Escape) {
-            fixed_time.toggle_pause(now);
+            scenes.toggle_pause(now);
+        }
+
+        let was_screen_tapped = touch_controls::was_screen_tapped();
+
+        if is_key_released(KeyCode::Enter) || was_screen_tapped {
+            scenes.advance(now);
+        }
+
+        if scenes.scene() == Scene::Paused && was_screen_tapped {
+            scenes.toggle_pause(now);
         }
 
         #[cfg(target_arch = "wasm32")]
-        if js::is_blurred() && !fixed_time.is_paused() {
-            fixed_time.set_paused(true, now);
+        if js::is_blurred() {
+            scenes.pause(now);
         }
 
         if is_key_pressed(KeyCode::G) {
             enable_debug_mode = !enable_debug_mode;
         }
 
+        for (key, name) in SAVE_SLOT_KEYS {
+            if is_key_released(key) {
+                current_save_slot = name;
+                println!("Selected save slot {}.", name);
+            }
+        }
+
         if is_key_released(KeyCode::F5) {
-            saved_state = Some((level_runtime.save(), fixed_time.create_paused_clone()));
-            println!("Saved state.");
+            let slot = SaveSlot::new(scenes.level_runtime(), &fixed_time, scenes.world());
+            match save_slots.save(current_save_slot, slot) {
+                Ok(()) => println!("Saved state to slot {}.", current_save_slot),
+                Err(e) => println!("Unable to save state: {}", e),
+            }
         }
 
         if is_key_released(KeyCode::F9) {
-            if let Some((saved_level_runtime, paused_time)) = saved_state.as_ref() {
-                level_runtime = LevelRuntime::from_saved(saved_level_runtime.clone());
-                input_state = InputState::default();
-                let mut new_fixed_time = paused_time.clone();
-                new_fixed_time.set_paused(fixed_time.is_paused(), now);
-                fixed_time = new_fixed_time;
-                println!("Loaded state.");
+            if is_recording_or_playing_back {
+                // Like rewinding, this would make the recording impossible to replay.
+                println!("Can't load saved games while recording or playing back.");
+            } else if let Some(slot) = save_slots.get(current_save_slot) {
+                if slot.world_digest != scenes.world().digest() {
+                    println!(
+                        "Warning: slot {} was saved with a different world.",
+                        current_save_slot
+                    );
+                }
+                match LevelRuntime::from_saved(slot.level_runtime.clone(), scenes.world().clone()) {
+                    Ok(loaded_level_runtime) => {
+                        scenes.set_level_runtime(loaded_level_runtime);
+                        input_state = InputState::default();
+                        let mut new_fixed_time = slot.time.clone();
+                        new_fixed_time.set_paused(fixed_time.is_paused(), now);
+                        fixed_time = new_fixed_time;
+                        rewind_buffer.clear();
+                        println!("Loaded state from slot {}.", current_save_slot);
+                    }
+                    Err(e) => println!("Unable to load state: {}", e),
+                }
             } else {
-                println!("No saved state exists!");
+                println!("No saved state exists in slot {}!", current_save_slot);
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some([config_watcher, world_watcher]) = &mut watchers {
+            let config_changed = config_watcher.has_changed(now);
+            let world_changed = world_watcher.has_changed(now);
+            if config_changed || world_changed {
+                let result = reload_world(&args, &loader, scenes.world(), config_changed).await;
+                match result.and_then(|world| scenes.reload_world(world)) {
+                    Ok(()) => {
+                        // Rewind snapshots are from the old world.
+                        rewind_buffer.clear();
+                        println!("Reloaded the config and world.");
+                    }
+                    Err(e) => println!("Unable to reload the config and world: {}", e),
+                }
             }
         }
 
         if enable_debug_mode {
             let debug_mode = opt_debug_mode.get_or_insert_with(|| DebugMode::default());
             debug_mode
-                .update(&level_runtime, &fixed_fps, &render_fps)
+                .update(scenes.level_runtime(), &fixed_fps, &render_fps)
                 .expect("Generating debug text should work!");
-            debug_mode.draw(&level_runtime);
+            debug_mode.draw(scenes.level_runtime());
         }
 
-        if fixed_time.is_paused() {
-            draw_pause_overlay(is_browser);
-            if !is_browser && is_key_released(KeyCode::Q) {
-                break;
-            }
+        if scenes.scene() == Scene::Paused && !is_browser && is_key_released(KeyCode::Q) {
+            break;
         }
 
         next_frame().await;
         frame_number += 1;
     }
 }
-
-fn new_game(start_position: &str, world: Rc<World>) -> LevelRuntime {
-    let (level_start, player_start) = world.player_start(start_position).expect(&format!(
-        "World does not define a PlayerStart entity called '{}'!",
-        start_position
-    ));
-    LevelRuntime::new(create_player(player_start, "PLAYER"), level_start, world)
-}
@@ -1,13 +1,15 @@
 use anyhow::Result;
-use macroquad::{
-    prelude::{
-        gl_use_default_material, gl_use_material, load_material, load_string, Color, Material,
-        MaterialParams, UniformType,
-    },
-    texture::Image,
+use macroquad::prelude::{
+    gl_use_default_material, gl_use_material, load_material, load_string, Color, Material,
+    MaterialParams, UniformType,
 };
 
-use crate::game_assets::game_assets;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    game_assets::{GameAssets, ImageId},
+    serde_util::SerializableColor,
+};
 
 const BASE_SHADER_PATH: &str = "media/shaders";
 
@@ -26,7 +28,7 @@ const LERP_TYPE_REPLACED_COLOR: i32 = 1;
 const LERP_TYPE_ALL_COLORS: i32 = 2;
 
 #[allow(dead_code)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum LerpType {
     /// Linearly interpolate only pixels containing the replaced colors from
     /// their replaced values to a specified color (unless the replaced colors
@@ -41,7 +43,7 @@ pub struct GameMaterials {
     pub replace_color_material: Material,
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 pub struct ReplaceColorOptions {
     /// Find/replace colors using the given image, linearly interpolating
     /// between the find color and replace color by the given amount.
@@ -53,13 +55,35 @@ pub struct ReplaceColorOptions {
     /// pixel, a red pixel, a green pixel, and a yellow pixel, this means that
     /// whenever this material is used, all blue pixels will be replaced by red
     /// ones, and all green pixels will be replaced by yellow ones.
-    pub image: Option<(&'static Image, f32)>,
+    pub image: Option<(ImageId, f32)>,
     /// Linearly interpolate to the given color by the given amount, using
     /// the specified scheme.
+    #[serde(with = "option_lerp")]
     pub lerp: Option<(LerpType, Color, f32)>,
 }
 
-#[derive(Default, Clone, Copy)]
+mod option_lerp {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<(LerpType, Color, f32)>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value
+            .map(|(lerp_type, color, amount)| (lerp_type, SerializableColor(color), amount))
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<(LerpType, Color, f32)>, D::Error> {
+        let value: Option<(LerpType, SerializableColor, f32)> =
+            Deserialize::deserialize(deserializer)?;
+        Ok(value.map(|(lerp_type, color, amount)| (lerp_type, color.0, amount)))
+    }
+}
+
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 pub enum MaterialRenderer {
     #[default]
     None,
@@ -67,11 +91,11 @@ pub enum MaterialRenderer {
 }
 
 impl MaterialRenderer {
-    pub fn start_using(&self) {
+    pub fn start_using(&self, assets: &GameAssets) {
         match self {
             MaterialRenderer::None => {}
             MaterialRenderer::ReplaceColors(options) => {
-                use_replace_color_material(options);
+                use_replace_color_material(options, assets);
             }
         }
     }
@@ -88,15 +112,17 @@ impl MaterialRenderer {
 
 /// Use an image to specify what colors to replace at render time.
 /// For details on the format of the image, see [`ReplaceColorOptions`].
-pub fn replace_colors_with_image(image: &'static Image) -> MaterialRenderer {
+pub fn replace_colors_with_image(image: ImageId) -> MaterialRenderer {
     MaterialRenderer::ReplaceColors(ReplaceColorOptions {
         image: Some((image, 1.0)),
         ..Default::default()
     })
 }
 
-fn use_replace_color_material(options: &ReplaceColorOptions) {
-    let materials = &game_assets().materials;
+fn use_replace_color_material(options: &ReplaceColorOptions, assets: &GameAssets) {
+    let Some(materials) = &assets.materials else {
+        return;
+    };
     let material = materials.replace_color_material;
     gl_use_material(material);
 
@@ -116,10 +142,11 @@ fn use_replace_color_material(options: &ReplaceColorOptions) {
         }
     }
 
-    let Some((image, find_replace_lerp_amount)) = options.image else {
+    let Some((image_id, find_replace_lerp_amount)) = options.image else {
         material.set_uniform("num_replacements", 0);
         return;
     };
+    let image = assets.image(image_id);
 
     let num_replacements = (image.width / 2) as i32;
 
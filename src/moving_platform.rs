@@ -2,7 +2,7 @@ use macroquad::prelude::{Rect, PINK};
 
 use crate::{
     collision::CollisionFlags,
-    config::config,
+    context::GameContext,
     dynamic_collider::{DynamicColliderComponent, RelativeCollider},
     entity::Entity,
     level::{MovingPlatformArgs, RendererType},
@@ -11,7 +11,11 @@ use crate::{
     sprite_component::{Renderer, SpriteComponent},
 };
 
-pub fn create_moving_platform(start_rect: Rect, args: &MovingPlatformArgs) -> Entity {
+pub fn create_moving_platform(
+    start_rect: Rect,
+    args: &MovingPlatformArgs,
+    ctx: &GameContext,
+) -> Entity {
     let mut sprite = SpriteComponent::default().with_pos_and_size(&start_rect);
     match args.renderer_type {
         RendererType::EntityTiles => {
@@ -45,7 +49,7 @@ pub fn create_moving_platform(start_rect: Rect, args: &MovingPlatformArgs) -> En
             is_moving: args.ping_pong,
             ping_pong: args.ping_pong,
             is_moving_towards_start: false,
-            speed: config().moving_platform_speed,
+            speed: ctx.config.moving_platform_speed,
         }),
         ..Default::default()
     };
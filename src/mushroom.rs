@@ -2,11 +2,11 @@ use macroquad::prelude::Rect;
 
 use crate::{
     animator::Animator,
-    audio::play_sound_effect,
-    config::config,
+    context::GameContext,
     dynamic_collider::{DynamicColliderComponent, RelativeCollider},
     entity::{filter_and_process_entities, Entity, EntityMap},
-    game_assets::game_assets,
+    events::GameEvent,
+    game_assets::{GameAssets, ImageId},
     life_transfer::{get_life_receiving_amount_or_zero, LifeTransfer},
     materials::{replace_colors_with_image, LerpType, MaterialRenderer, ReplaceColorOptions},
     physics::{PhysicsCollisionBehavior, PhysicsComponent},
@@ -14,29 +14,29 @@ use crate::{
     time::GameTime,
 };
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct MushroomComponent {
     state: MushroomState,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum MushroomState {
     Dead,
     Rezzing(Animator),
     Alive,
 }
 
-fn dead_frame() -> u32 {
-    game_assets().mushroom.death.last_frame()
+fn dead_frame(assets: &GameAssets) -> u32 {
+    assets.mushroom.death.last_frame()
 }
 
-pub fn create_mushrom(start_rect: Rect) -> Entity {
-    let assets = &game_assets().mushroom;
+pub fn create_mushrom(start_rect: Rect, ctx: &GameContext) -> Entity {
+    let assets = &ctx.assets.mushroom;
     let death_sprite = &assets.death;
     Entity {
         sprite: SpriteComponent {
             base_relative_bbox: assets.dead_bbox,
-            sprite: Some(&death_sprite),
+            sprite: Some(death_sprite.handle()),
             left_facing_rendering: LeftFacingRendering::FlipBoundingBox,
             ..Default::default()
         }
@@ -54,42 +54,54 @@ pub fn create_mushrom(start_rect: Rect) -> Entity {
     }
 }
 
-pub fn mushroom_movement_system(entities: &mut EntityMap, time: &GameTime) {
+pub fn mushroom_movement_system(
+    entities: &mut EntityMap,
+    events: &mut Vec<GameEvent>,
+    time: &GameTime,
+    ctx: &GameContext,
+) {
     filter_and_process_entities(
         entities,
         |entity| entity.mushroom.is_some(),
-        |entity, _entities, _id| {
-            update_mushroom(entity, time);
+        |entity, _entities, id| {
+            if update_mushroom(entity, time, ctx) {
+                events.push(GameEvent::MushroomRezzed {
+                    id,
+                    iid: entity.iid,
+                });
+            }
         },
     );
 }
 
-fn update_mushroom(entity: &mut Entity, time: &GameTime) {
+/// Returns whether the mushroom just started being brought back to life.
+fn update_mushroom(entity: &mut Entity, time: &GameTime, ctx: &GameContext) -> bool {
     let mushroom = entity.mushroom.as_mut().unwrap();
     let velocity = &mut entity.physics.velocity;
     let sprite = &mut entity.sprite;
     let dynamic_collider = &mut entity.dynamic_collider;
-    let config = config();
-    let assets = &game_assets().mushroom;
+    let config = &ctx.config;
+    let assets = &ctx.assets.mushroom;
+    let mut started_rezzing = false;
 
     match &mushroom.state {
         MushroomState::Dead => {
             let life_receiving = get_life_receiving_amount_or_zero(entity.life_transfer);
             if life_receiving == 1.0 {
                 mushroom.state = MushroomState::Rezzing(
-                    Animator::new(dead_frame(), true, &time)
+                    Animator::new(dead_frame(&ctx.assets), true, &time, config)
                         .with_ms_per_animation_frame(config.mushroom_rez_ms_per_animation_frame),
                 );
                 entity.life_transfer = None;
                 sprite.base_relative_bbox = assets.idle_bbox;
-                play_sound_effect(game_assets().mushroom.rez_sound);
+                started_rezzing = true;
             }
         }
         MushroomState::Rezzing(animator) => {
             if animator.is_done(&time) {
                 mushroom.state = MushroomState::Alive;
-                sprite.sprite = Some(&assets.run);
-                sprite.material = replace_colors_with_image(&assets.color_replacements);
+                sprite.sprite = Some(assets.run.handle());
+                sprite.material = replace_colors_with_image(ImageId::MushroomColorReplacements);
                 velocity.x = config.mushroom_speed;
                 let _ = dynamic_collider.insert(DynamicColliderComponent::new(RelativeCollider {
                     rect: assets.platform_bbox,
@@ -102,24 +114,30 @@ fn update_mushroom(entity: &mut Entity, time: &GameTime) {
             sprite.is_facing_left = velocity.x < 0.;
         }
     }
-    mushroom.set_sprite(time, sprite, entity.life_transfer);
+    mushroom.set_sprite(time, sprite, entity.life_transfer, &ctx.assets);
+    started_rezzing
 }
 
 impl MushroomComponent {
+    pub fn is_dead(&self) -> bool {
+        matches!(self.state, MushroomState::Dead)
+    }
+
     fn set_sprite(
         &self,
         time: &GameTime,
         sprite: &mut SpriteComponent,
         life_transfer: Option<LifeTransfer>,
+        assets: &GameAssets,
     ) {
         match &self.state {
             MushroomState::Dead => {
-                sprite.current_frame_number = dead_frame();
-                let glow_image = &game_assets().huntress.spear_glow_color_replacements;
+                sprite.current_frame_number = dead_frame(assets);
+                let glow_image = &assets.huntress.spear_glow_color_replacements;
                 let glow_color = glow_image.get_pixel((glow_image.width as u32) - 1, 0);
                 let amount = get_life_receiving_amount_or_zero(life_transfer);
                 sprite.material = MaterialRenderer::ReplaceColors(ReplaceColorOptions {
-                    image: Some((&game_assets().mushroom.dead_color_replacements, 1.)),
+                    image: Some((ImageId::MushroomDeadColorReplacements, 1.)),
                     lerp: Some((LerpType::ReplacedColor, glow_color, amount)),
                 })
             }
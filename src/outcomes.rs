@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+
+use uuid::Uuid;
+
+use crate::{
+    events::{GameEvent, PickupType},
+    level_runtime::LevelRuntime,
+    world::World,
+};
+
+/// Keeps track of what the player has accomplished over the course of a session,
+/// by observing the game's events after every frame.
+#[derive(Default, Clone)]
+pub struct SessionOutcomes {
+    furthest_level: Option<(usize, String)>,
+    levels_reached: HashSet<String>,
+    collected_gems: HashSet<Uuid>,
+    rezzed_mushrooms: HashSet<Uuid>,
+    picked_up_spear: bool,
+}
+
+impl SessionOutcomes {
+    pub fn update(&mut self, level_runtime: &LevelRuntime, world: &World) {
+        let level = level_runtime.level();
+
+        if !self.levels_reached.contains(&level.identifier) {
+            self.levels_reached.insert(level.identifier.clone());
+            if let Some(index) = world.level_index(&level.identifier) {
+                let is_further = match &self.furthest_level {
+                    Some((furthest_index, _)) => index > *furthest_index,
+                    None => true,
+                };
+                if is_further {
+                    self.furthest_level = Some((index, level.identifier.clone()));
+                }
+            }
+        }
+
+        for event in level_runtime.events() {
+            match event {
+                GameEvent::PickupCollected {
+                    kind: PickupType::Gem,
+                    pickup_iid: Some(iid),
+                    ..
+                } => {
+                    self.collected_gems.insert(*iid);
+                }
+                GameEvent::PickupCollected {
+                    kind: PickupType::Spear,
+                    ..
+                } => {
+                    self.picked_up_spear = true;
+                }
+                GameEvent::MushroomRezzed { iid: Some(iid), .. } => {
+                    self.rezzed_mushrooms.insert(*iid);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The identifier of the furthest level reached, based on the order in
+    /// which levels are defined in the world.
+    pub fn furthest_level(&self) -> Option<&str> {
+        self.furthest_level.as_ref().map(|(_, id)| id.as_str())
+    }
+
+    /// How many distinct levels were reached.
+    pub fn levels_reached(&self) -> usize {
+        self.levels_reached.len()
+    }
+
+    /// How many distinct gems were collected. Gems reappear when the game is
+    /// restarted, but collecting the same gem twice only counts once.
+    pub fn gems_collected(&self) -> usize {
+        self.collected_gems.len()
+    }
+
+    /// How many distinct mushrooms were brought back to life.
+    pub fn mushrooms_rezzed(&self) -> usize {
+        self.rezzed_mushrooms.len()
+    }
+
+    pub fn picked_up_spear(&self) -> bool {
+        self.picked_up_spear
+    }
+}
@@ -5,10 +5,12 @@ use crate::{
         collision_resolution_loop, maybe_reverse_direction_x, maybe_reverse_direction_xy,
         process_collision, Collider, CollisionFlags, Side,
     },
-    config::config,
+    context::GameContext,
     dynamic_collider::DynamicColliderSystem,
-    entity::{Entity, EntityMap, HeaplessEntityVec},
+    entity::{Entity, EntityMap},
     level::Level,
+    serde_util::RectDef,
+    spatial_index::SpatialIndex,
     time::GameTime,
 };
 
@@ -16,7 +18,7 @@ use crate::{
 /// collision resolution, start logging debug information.
 const LOTS_OF_DISPLACEMENTS: u32 = 20;
 
-#[derive(Default, PartialEq, Clone, Copy)]
+#[derive(Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum PhysicsCollisionBehavior {
     #[default]
     /// This effectively disables collisions for the entity.
@@ -32,7 +34,7 @@ pub enum PhysicsCollisionBehavior {
     ReverseDirectionXY,
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 pub struct PhysicsComponent {
     /// The current velocity of the entity.
     pub velocity: Vec2,
@@ -62,6 +64,7 @@ pub struct PhysicsComponent {
 
     /// The bounding box of the entity in the *last* frame.
     /// This should really be read-only, but it's easiest to just make it public.
+    #[serde(with = "RectDef")]
     pub prev_bbox: Rect,
 
     /// Results of the latest iteration of the physics loop.
@@ -69,7 +72,7 @@ pub struct PhysicsComponent {
     pub latest_frame: PhysicsFrameResults,
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 pub struct PhysicsFrameResults {
     pub is_on_any_surface: bool,
     pub is_on_moving_surface: bool,
@@ -82,12 +85,17 @@ pub struct PhysicsFrameResults {
 ///
 /// After this runs, some entities may have positions that are inside others;
 /// call `physics_system_resolve_collisions` to resolve them.
-pub fn physics_system_update_positions(entities: &mut EntityMap, time: &GameTime) {
-    let gravity = config().gravity;
+pub fn physics_system_update_positions(
+    entities: &mut EntityMap,
+    spatial_index: &mut SpatialIndex,
+    time: &GameTime,
+    ctx: &GameContext,
+) {
+    let gravity = ctx.config.gravity;
     let time_since_last_frame = time.time_since_last_frame as f32;
     let gravity_this_frame = gravity * time_since_last_frame;
 
-    for (_id, entity) in entities.iter_mut() {
+    for (id, entity) in entities.iter_mut() {
         if !entity.physics.defies_gravity {
             entity.physics.velocity.y +=
                 gravity_this_frame * entity.physics.gravity_coefficient.unwrap_or(1.0);
@@ -98,6 +106,7 @@ pub fn physics_system_update_positions(entities: &mut EntityMap, time: &GameTime
         entity.sprite.pos += entity.physics.velocity * time_since_last_frame;
         entity.sprite.pos.x += entity.physics.x_impulse * time_since_last_frame;
         entity.physics.x_impulse = 0.;
+        spatial_index.update(id, entity.sprite.bbox());
     }
 }
 
@@ -105,13 +114,14 @@ pub fn physics_system_update_positions(entities: &mut EntityMap, time: &GameTime
 /// `physics_system_update_positions`.
 pub fn physics_system_resolve_collisions(
     entities: &mut EntityMap,
+    spatial_index: &mut SpatialIndex,
     level: &Level,
     dynamic_collider_system: &mut DynamicColliderSystem,
+    ctx: &GameContext,
 ) {
-    let vertical_collision_leeway = config().vertical_collision_leeway;
+    let vertical_collision_leeway = ctx.config.vertical_collision_leeway;
 
-    let mut entities_to_process: HeaplessEntityVec = heapless::Vec::new();
-    entities_to_process.extend(entities.ids().cloned());
+    let mut entities_to_process: Vec<u64> = entities.ids().collect();
 
     // Sort our entities from bottom to top. This ensures that any displacements
     // caused by the effects of gravity will propagate upwards, e.g. that the
@@ -133,7 +143,7 @@ pub fn physics_system_resolve_collisions(
                 |bbox| {
                     level
                         .iter_colliders_ex(bbox, !defies_level_bounds)
-                        .chain(dynamic_collider_system.colliders().copied())
+                        .chain(dynamic_collider_system.colliders_overlapping(bbox))
                 },
                 vertical_collision_leeway,
             )
@@ -141,6 +151,8 @@ pub fn physics_system_resolve_collisions(
             Default::default()
         };
 
+        spatial_index.update(id, entity.sprite.bbox());
+
         if results.was_displaced && entity.dynamic_collider.is_some() {
             // This entity has a dynamic collider associated with it, so update its
             // computed collider to reflect its displaced position. This will ensure
@@ -290,7 +302,7 @@ mod tests {
     use macroquad::prelude::{Rect, Vec2};
 
     use crate::{
-        collision::{Collider, CollisionFlags, EXTRA_DISPLACEMENT},
+        collision::{Collider, ColliderShape, CollisionFlags, EXTRA_DISPLACEMENT},
         entity::Entity,
         physics::PhysicsComponent,
         sprite_component::SpriteComponent,
@@ -353,6 +365,40 @@ mod tests {
         }
     }
 
+    fn make_one_way_collider(rect: Rect) -> Collider {
+        Collider {
+            rect,
+            prev_rect: rect,
+            flags: CollisionFlags::ENVIRONMENT,
+            enable_top: true,
+            ..Default::default()
+        }
+    }
+
+    fn make_slope_collider(rect: Rect, left: f32, right: f32) -> Collider {
+        Collider {
+            rect,
+            prev_rect: rect,
+            flags: CollisionFlags::ENVIRONMENT,
+            enable_top: true,
+            enable_bottom: true,
+            enable_left: left > right,
+            enable_right: right > left,
+            shape: ColliderShape::Slope { left, right },
+            ..Default::default()
+        }
+    }
+
+    fn assert_bottom_is_just_above(entity: &Entity, y: f32) {
+        let delta = y - entity.sprite.bbox().bottom();
+        assert!(
+            delta > 0. && delta < EXTRA_DISPLACEMENT * 2.,
+            "Expected bottom of entity to be just above {}, but it is {}",
+            y,
+            entity.sprite.bbox().bottom()
+        );
+    }
+
     trait EntityHelpers
     where
         Self: Sized,
@@ -442,4 +488,46 @@ mod tests {
         assert!(!results.is_penetrating_collider);
         assert!(entity.is_just_left_of(top_collider));
     }
+
+    #[test]
+    fn test_entities_land_on_one_way_colliders() {
+        let mut entity = make_simple_10x10_entity().with_previous_velocity(Vec2::new(0., 1.));
+        let collider = make_one_way_collider(entity.offset_down_by(9));
+        let results = simple_collision_resolution(&mut entity, vec![collider]);
+        assert!(results.is_on_any_surface);
+        assert_bottom_is_just_above(&entity, collider.rect.top());
+    }
+
+    #[test]
+    fn test_entities_pass_through_one_way_colliders_from_below() {
+        let mut entity = make_simple_10x10_entity().with_previous_velocity(Vec2::new(0., -2.));
+        let collider = make_one_way_collider(entity.offset_up_by(5));
+        let results = simple_collision_resolution(&mut entity, vec![collider]);
+        assert!(!results.was_displaced);
+    }
+
+    #[test]
+    fn test_entities_are_displaced_up_slopes() {
+        // The entity was standing on the slope and has moved right, up it.
+        let mut entity = make_simple_10x10_entity().with_previous_velocity(Vec2::new(1., 0.));
+        let collider = make_slope_collider(Rect::new(10., 14.5, 10., 10.), 0., 1.);
+        let results = simple_collision_resolution(&mut entity, vec![collider]);
+        assert!(results.is_on_any_surface);
+        assert_bottom_is_just_above(&entity, 19.5);
+    }
+
+    #[test]
+    fn test_entities_stay_on_slopes_they_walk_down() {
+        // The entity was standing on the slope and has moved right, down it.
+        let mut entity = make_simple_10x10_entity().with_previous_velocity(Vec2::new(1., 0.));
+        let collider = make_slope_collider(Rect::new(10., 16., 10., 10.), 1., 0.);
+        let results = physics_collision_resolution(
+            SIMPLE_ENTITY_ID,
+            &mut entity,
+            |_bbox| [collider].into_iter(),
+            0.5,
+        );
+        assert!(results.is_on_any_surface);
+        assert_bottom_is_just_above(&entity, 21.);
+    }
 }
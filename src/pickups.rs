@@ -1,22 +1,22 @@
 use macroquad::prelude::Rect;
 
 use crate::{
-    audio::play_sound_effect,
-    config::config,
-    entity::{filter_and_process_entities, Entity, EntityMap, HeaplessEntityVec},
-    game_assets::game_assets,
+    context::GameContext,
+    entity::{filter_and_process_entities, Entity, EntityMap},
+    events::GameEvent,
     physics::PhysicsComponent,
+    spatial_index::SpatialIndex,
     sprite_component::{Rotation, SpriteComponent},
     time::GameTime,
 };
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum PickupType {
     Spear,
     Gem,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct PickupComponent {
     kind: PickupType,
     base_y: f32,
@@ -30,14 +30,14 @@ fn create_pickup(kind: PickupType, mut entity: Entity) -> Entity {
     entity
 }
 
-pub fn create_gem(start_rect: Rect) -> Entity {
-    let assets = &game_assets().gem;
+pub fn create_gem(start_rect: Rect, ctx: &GameContext) -> Entity {
+    let assets = &ctx.assets.gem;
     create_pickup(
         PickupType::Gem,
         Entity {
             sprite: SpriteComponent {
                 base_relative_bbox: assets.gem.frame_rect(),
-                sprite: Some(&assets.gem),
+                sprite: Some(assets.gem.handle()),
                 ..Default::default()
             }
             .at_bottom_left(&start_rect),
@@ -50,14 +50,14 @@ pub fn create_gem(start_rect: Rect) -> Entity {
     )
 }
 
-pub fn create_spear(start_rect: Rect) -> Entity {
-    let assets = &game_assets().spear;
+pub fn create_spear(start_rect: Rect, ctx: &GameContext) -> Entity {
+    let assets = &ctx.assets.spear;
     create_pickup(
         PickupType::Spear,
         Entity {
             sprite: SpriteComponent {
                 base_relative_bbox: assets.spear_move_bbox,
-                sprite: Some(&assets.spear_move),
+                sprite: Some(assets.spear_move.handle()),
                 rotation: Rotation::Clockwise270,
                 ..Default::default()
             }
@@ -75,37 +75,51 @@ fn grab_pickup(player_entity: &mut Entity, pickup: PickupType) {
     let mut player = player_entity.player.as_mut().unwrap();
     match pickup {
         PickupType::Spear => {
-            player.has_spear = true;
-            play_sound_effect(game_assets().spear.pickup_sound);
+            player.inventory.has_spear = true;
         }
         PickupType::Gem => {
-            // TODO: Add a gem to the player's inventory.
-            play_sound_effect(game_assets().gem.pickup_sound);
+            player.inventory.gems += 1;
         }
     }
 }
 
-pub fn pickup_system(entities: &mut EntityMap, time: &GameTime) {
+pub fn pickup_system(
+    entities: &mut EntityMap,
+    spatial_index: &mut SpatialIndex,
+    events: &mut Vec<GameEvent>,
+    time: &GameTime,
+    ctx: &GameContext,
+) {
     filter_and_process_entities(
         entities,
         |entity| entity.player.is_some(),
-        |player_entity, entities, _| {
-            let mut entities_to_remove: HeaplessEntityVec = heapless::Vec::new();
-            for (id, entity) in entities.iter() {
+        |player_entity, entities, player_id| {
+            let mut entities_to_remove: Vec<u64> = vec![];
+            for id in spatial_index.query(&player_entity.sprite.bbox()) {
+                let Some(entity) = entities.get(id) else {
+                    continue;
+                };
                 if let Some(pickup) = entity.pickup {
                     if player_entity.sprite.bbox().overlaps(&entity.sprite.bbox()) {
                         grab_pickup(player_entity, pickup.kind);
-                        entities_to_remove.push(id).unwrap();
+                        events.push(GameEvent::PickupCollected {
+                            player_id,
+                            pickup_id: id,
+                            pickup_iid: entity.iid,
+                            kind: pickup.kind,
+                        });
+                        entities_to_remove.push(id);
                     }
                 }
             }
             for id in entities_to_remove {
                 entities.remove(id);
+                spatial_index.remove(id);
             }
         },
     );
 
-    let config = config();
+    let config = &ctx.config;
 
     for (_id, entity) in entities.iter_mut() {
         if let Some(pickup) = entity.pickup {
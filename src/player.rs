@@ -3,11 +3,12 @@ use std::rc::Rc;
 use macroquad::prelude::{Rect, Vec2};
 
 use crate::{
-    audio::play_sound_effect,
+    attachment::push_attachment_events,
     collision::CollisionFlags,
-    config::config,
-    entity::{filter_and_process_entities, Entity, EntityMap},
-    game_assets::game_assets,
+    context::GameContext,
+    entity::{filter_and_process_entities, Entity, EntityMap, MAIN_PLAYER_ENTITY_ID},
+    events::GameEvent,
+    game_assets::{GameAssets, ImageId},
     hierarchy::ChildComponent,
     input::{Buttons, InputState},
     level::Level,
@@ -17,27 +18,41 @@ use crate::{
     push::PushComponent,
     running::RunComponent,
     sprite_component::{LeftFacingRendering, Renderer, SpriteComponent},
-    sprite_renderer::SpriteRenderer,
+    sprite_renderer::SpriteHandle,
     time::GameTime,
     world::World,
     z_index::ZIndexComponent,
 };
 
-#[derive(Default, Copy, Clone)]
+/// The things the player has picked up.
+#[derive(Default, Copy, Clone, Serialize, Deserialize)]
+pub struct Inventory {
+    pub has_spear: bool,
+    pub gems: u32,
+}
+
+#[derive(Default, Copy, Clone, Serialize, Deserialize)]
 pub struct PlayerComponent {
     is_in_air: bool,
     coyote_time_start: Option<f64>,
+    /// When the jump button was last pressed, if it hasn't resulted in a jump
+    /// yet. This lets the player press jump slightly before they land.
+    jump_buffer_start: Option<f64>,
     run_direction: f32,
-    pub has_spear: bool,
+    pub inventory: Inventory,
     spear_point_entity: Option<u64>,
 }
 
-pub fn create_player(start_rect: Rect, name_for_debugging: &'static str) -> Entity {
-    let assets = &game_assets().huntress;
+pub fn create_player(
+    start_rect: Rect,
+    name_for_debugging: &'static str,
+    ctx: &GameContext,
+) -> Entity {
+    let assets = &ctx.assets.huntress;
     Entity {
         sprite: SpriteComponent {
             base_relative_bbox: assets.idle_bbox,
-            sprite: Some(&assets.idle),
+            sprite: Some(assets.idle.handle()),
             ..Default::default()
         }
         .at_bottom_left(&start_rect),
@@ -84,22 +99,37 @@ fn maybe_steer_attachment(attached_id: u64, entities: &mut EntityMap, input: &In
     }
 }
 
-pub fn process_player_input(entities: &mut EntityMap, time: &GameTime, input: &InputState) {
+pub fn process_player_input(
+    entities: &mut EntityMap,
+    events: &mut Vec<GameEvent>,
+    time: &GameTime,
+    input: &InputState,
+    ctx: &GameContext,
+) {
     let player = entities.main_player_mut();
     let attachment = player.attachment.as_mut().unwrap();
     if let Some(attached_id) = attachment.attached_to_entity_id() {
         if input.is_pressed(Buttons::JUMP) {
             attachment.detach(&mut player.physics);
+            push_attachment_events(MAIN_PLAYER_ENTITY_ID, Some(attached_id), attachment, events);
         } else {
             maybe_steer_attachment(attached_id, entities, input);
         }
-    } else {
-        unattached_player_process_input(player, time, input);
+    } else if unattached_player_process_input(player, time, input, ctx) {
+        events.push(GameEvent::PlayerJumped {
+            id: MAIN_PLAYER_ENTITY_ID,
+        });
     }
 }
 
-pub fn player_update_system(entities: &mut EntityMap, time: &GameTime) {
-    let config = config();
+pub fn player_update_system(
+    entities: &mut EntityMap,
+    events: &mut Vec<GameEvent>,
+    time: &GameTime,
+    ctx: &GameContext,
+) {
+    let config = &ctx.config;
+    let assets = &ctx.assets;
     filter_and_process_entities(
         entities,
         |entity| entity.player.is_some(),
@@ -112,9 +142,14 @@ pub fn player_update_system(entities: &mut EntityMap, time: &GameTime) {
 
             if physics.latest_frame.is_on_any_surface {
                 // The player just landed (or remains on the ground).
+                if player.is_in_air {
+                    events.push(GameEvent::PlayerLanded { id: player_id });
+                }
                 player.is_in_air = false;
                 player.coyote_time_start = None;
+                let was_attached_to = attachment.attached_to_entity_id();
                 attachment.reset(physics);
+                push_attachment_events(player_id, was_attached_to, attachment, events);
             } else if !player.is_in_air {
                 if let Some(coyote_start_time) = &player.coyote_time_start {
                     if time.now - coyote_start_time > config.coyote_time_ms / 1000. {
@@ -141,6 +176,7 @@ pub fn player_update_system(entities: &mut EntityMap, time: &GameTime) {
                 player.is_in_air,
                 &physics.velocity,
                 player.run_direction,
+                assets,
             ));
             sprite.left_facing_x_offset = if attachment.is_attached() {
                 // The player juts out awkwardly from their carrier if offset,
@@ -150,11 +186,11 @@ pub fn player_update_system(entities: &mut EntityMap, time: &GameTime) {
                 config.player_left_facing_x_offset
             };
             sprite.update_looping_frame_number(time);
-            if player.has_spear {
-                update_spear(player, player_id, sprite, entities);
+            if player.inventory.has_spear {
+                update_spear(player, player_id, sprite, entities, assets);
             } else {
                 sprite.material =
-                    replace_colors_with_image(&game_assets().huntress.no_spear_color_replacements);
+                    replace_colors_with_image(ImageId::HuntressNoSpearColorReplacements);
             }
         },
     );
@@ -165,18 +201,22 @@ fn update_spear(
     player_id: u64,
     sprite: &mut SpriteComponent,
     entities: &mut EntityMap,
+    assets: &GameAssets,
 ) {
     let spear_point_entity_id = match player.spear_point_entity {
-        None => {
+        Some(id) if !entities.is_stale(id) => id,
+        stale_id => {
+            if stale_id.is_some() {
+                println!("Warning: stale spear point entity id, creating a new one!");
+            }
             let spear_point_id = entities.new_id();
             entities.insert(
                 spear_point_id,
-                create_spear_point_entity(player_id, &sprite),
+                create_spear_point_entity(player_id, &sprite, assets),
             );
             player.spear_point_entity = Some(spear_point_id);
             spear_point_id
         }
-        Some(id) => id,
     };
     let spear_glow_amount = if let Some(spear_point_entity) = entities.get(spear_point_entity_id) {
         get_life_giving_amount_or_zero(spear_point_entity.life_transfer)
@@ -186,22 +226,26 @@ fn update_spear(
     };
     sprite.material = MaterialRenderer::ReplaceColors(ReplaceColorOptions {
         image: Some((
-            &game_assets().huntress.spear_glow_color_replacements,
+            ImageId::HuntressSpearGlowColorReplacements,
             spear_glow_amount,
         )),
         ..Default::default()
     });
 }
 
-fn create_spear_point_entity(player_id: u64, player_sprite: &SpriteComponent) -> Entity {
-    let assets = &game_assets().huntress;
+fn create_spear_point_entity(
+    player_id: u64,
+    player_sprite: &SpriteComponent,
+    assets: &GameAssets,
+) -> Entity {
+    let assets = &assets.huntress;
 
     Entity {
         sprite: SpriteComponent {
             base_relative_bbox: assets.spear_point_bbox,
             left_facing_rendering: LeftFacingRendering::FlipBoundingBox,
             left_facing_bbox_x_offset: player_sprite.left_facing_x_offset,
-            sprite: Some(&assets.idle),
+            sprite: Some(assets.idle.handle()),
             renderer: Renderer::Invisible,
             ..Default::default()
         },
@@ -211,22 +255,33 @@ fn create_spear_point_entity(player_id: u64, player_sprite: &SpriteComponent) ->
     }
 }
 
+/// Returns whether the player jumped.
 fn unattached_player_process_input(
     player_entity: &mut Entity,
     time: &GameTime,
     input: &InputState,
-) {
+    ctx: &GameContext,
+) -> bool {
     let time_since_last_frame = time.time_since_last_frame;
-    let config = config();
+    let config = &ctx.config;
     let physics = &mut player_entity.physics;
     let run = player_entity.run.as_mut().unwrap();
     let player = player_entity.player.as_mut().unwrap();
+    let mut jumped = false;
     run.update(
         time_since_last_frame,
         input.is_down(Buttons::LEFT),
         input.is_down(Buttons::RIGHT),
+        config,
     );
 
+    if input.is_pressed(Buttons::JUMP) {
+        player.jump_buffer_start = Some(time.now);
+    }
+    let wants_to_jump = player
+        .jump_buffer_start
+        .is_some_and(|start| time.now - start <= config.jump_buffer_ms / 1000.);
+
     if player.is_in_air {
         if input.is_down(Buttons::JUMP) && physics.velocity.y < 0. {
             physics.velocity.y -=
@@ -236,42 +291,44 @@ fn unattached_player_process_input(
             physics.velocity.x = run.run_speed();
         }
     } else {
-        if input.is_pressed(Buttons::JUMP) {
+        if wants_to_jump {
+            player.jump_buffer_start = None;
             let new_velocity = Vec2::new(run.run_speed(), -config.jump_velocity);
             physics.velocity.x = new_velocity.x;
             physics.velocity.y = new_velocity.y;
             player.is_in_air = true;
-            play_sound_effect(game_assets().huntress.jump_sound);
+            jumped = true;
         } else {
             physics.x_impulse = run.run_speed();
         }
     }
     player.run_direction = physics.x_impulse;
+    jumped
 }
 
 fn sprite_renderer(
     is_in_air: bool,
     velocity: &Vec2,
     run_direction: f32,
-) -> &'static SpriteRenderer {
-    let sprites = game_assets();
+    sprites: &GameAssets,
+) -> SpriteHandle {
     if is_in_air {
         if velocity.y >= 0. {
-            &sprites.huntress.fall
+            sprites.huntress.fall.handle()
         } else {
-            &sprites.huntress.jump
+            sprites.huntress.jump.handle()
         }
     } else {
         if run_direction != 0. {
-            &sprites.huntress.run
+            sprites.huntress.run.handle()
         } else {
-            &sprites.huntress.idle
+            sprites.huntress.idle.handle()
         }
     }
 }
 
-pub fn did_fall_off_level(sprite: &SpriteComponent, level: &Level) -> bool {
-    sprite.bbox().top() - level.pixel_bounds().bottom() > config().fall_off_level_threshold
+pub fn did_fall_off_level(sprite: &SpriteComponent, level: &Level, ctx: &GameContext) -> bool {
+    sprite.bbox().top() - level.pixel_bounds().bottom() > ctx.config.fall_off_level_threshold
 }
 
 pub fn should_switch_levels(
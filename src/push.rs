@@ -1,6 +1,9 @@
-use crate::entity::{filter_and_process_entities, EntityMap};
+use crate::{
+    entity::{filter_and_process_entities, EntityMap},
+    spatial_index::SpatialIndex,
+};
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 pub struct PushComponent {
     /// Whether the entity can push other entities.
     pub can_push: bool,
@@ -11,7 +14,7 @@ pub struct PushComponent {
     pub pushable_coefficient: f32,
 }
 
-pub fn push_system(entities: &mut EntityMap) {
+pub fn push_system(entities: &mut EntityMap, spatial_index: &mut SpatialIndex) {
     filter_and_process_entities(
         entities,
         |entity| {
@@ -22,7 +25,10 @@ pub fn push_system(entities: &mut EntityMap) {
                 .unwrap_or(false)
         },
         |pusher, entities, _| {
-            for (_id, pushed) in entities.iter_mut() {
+            for id in spatial_index.query(&pusher.sprite.bbox()) {
+                let Some(pushed) = entities.get_mut(id) else {
+                    continue;
+                };
                 if let Some(push) = &pushed.push {
                     if push.pushable_coefficient > 0. {
                         let pusher_bbox = pusher.sprite.bbox();
@@ -38,6 +44,7 @@ pub fn push_system(entities: &mut EntityMap) {
                                 };
                                 let x_delta = intersection.w * push.pushable_coefficient * sign;
                                 pushed.sprite.pos.x += x_delta;
+                                spatial_index.update(id, pushed.sprite.bbox());
                             }
                         }
                     }
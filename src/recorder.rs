@@ -1,28 +1,159 @@
 use std::{cell::RefCell, io::Write, rc::Rc};
 
-use crate::input::{Buttons, InputStream};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    checksum::{Desync, SharedChecksumChannel},
+    input::{Buttons, InputStream},
+    world::World,
+};
+
+/// By default, record a checksum of the game state every this many frames.
+pub const DEFAULT_CHECKSUM_INTERVAL: u64 = 300;
+
+/// Recordings that start with these bytes consist of a `RecordingHeader`, then
+/// (as of format version 2) a `RecordingMetadata`, followed by a sequence of
+/// `RecordedEvent`s.
+///
+/// Older recordings are just a sequence of `RecordedFrame`s. Since the first of
+/// those is always for frame zero, they always start with a zero byte, so they
+/// can't be confused with the newer format.
+const RECORDING_MAGIC: [u8; 4] = *b"MQFR";
+
+const RECORDING_FORMAT_VERSION: u32 = 2;
 
 #[derive(Serialize, Deserialize)]
-struct RecordedFrame {
-    frame_number: u64,
-    buttons: Buttons,
+struct RecordingHeader {
+    magic: [u8; 4],
+    format_version: u32,
+}
+
+/// Information about the circumstances under which a recording was made.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordingMetadata {
+    /// The version of the game, as specified in its `Cargo.toml`.
+    pub game_version: String,
+    pub start_position: String,
+    pub fixed_fps: u64,
+    pub config_digest: u64,
+    pub world_digest: u64,
+}
+
+impl RecordingMetadata {
+    /// Metadata for a recording made by the currently-running build of the game.
+    pub fn current(start_position: &str, world: &World) -> Self {
+        RecordingMetadata {
+            game_version: env!("CARGO_PKG_VERSION").to_owned(),
+            start_position: start_position.to_owned(),
+            fixed_fps: world.ctx().config.fixed_fps,
+            config_digest: world.ctx().config.digest,
+            world_digest: world.digest(),
+        }
+    }
+
+    /// Make sure that a recording with this metadata can be played back in the
+    /// given circumstances.
+    ///
+    /// Differences that make playback meaningless, like a different start position,
+    /// are errors. Differences that merely make it _likely_ that playback will
+    /// diverge from the original session, like a different version of the game,
+    /// just log warnings.
+    pub fn validate(&self, expected: &RecordingMetadata) -> Result<()> {
+        if self.start_position != expected.start_position {
+            return Err(anyhow!(
+                "Recording was made with start position '{}', but we are using '{}'.",
+                self.start_position,
+                expected.start_position
+            ));
+        }
+        if self.fixed_fps != expected.fixed_fps {
+            return Err(anyhow!(
+                "Recording was made at {} fixed FPS, but we are using {}.",
+                self.fixed_fps,
+                expected.fixed_fps
+            ));
+        }
+        if self.game_version != expected.game_version {
+            println!(
+                "WARNING: Recording was made with v{} of the game, but this is v{}.",
+                self.game_version, expected.game_version
+            );
+        }
+        if self.config_digest != expected.config_digest {
+            println!("WARNING: Recording was made with a different config.");
+        }
+        if self.world_digest != expected.world_digest {
+            println!("WARNING: Recording was made with a different world.");
+        }
+        Ok(())
+    }
+}
+
+/// The buttons that were held down as of the given frame. These are only
+/// recorded when the buttons change.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RecordedFrame {
+    pub frame_number: u64,
+    pub buttons: Buttons,
+}
+
+/// A checksum of the game state after the given frame has been simulated.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RecordedChecksum {
+    pub frame_number: u64,
+    pub checksum: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+enum RecordedEvent {
+    Frame(RecordedFrame),
+    Checksum(RecordedChecksum),
 }
 
 pub struct InputRecorder<W: Write> {
     source: InputStream,
     output: Rc<RefCell<W>>,
+    checksums: SharedChecksumChannel,
     prev_buttons: Option<Buttons>,
     frame_number: u64,
 }
 
 impl<W: Write + 'static> InputRecorder<W> {
-    pub fn new(source: InputStream, output: Rc<RefCell<W>>) -> InputStream {
-        Box::new(InputRecorder {
+    /// Record the given input stream to the given output. Checksums of the game
+    /// state will also be recorded, if the given checksum channel asks for them.
+    pub fn new(
+        source: InputStream,
+        output: Rc<RefCell<W>>,
+        metadata: &RecordingMetadata,
+        checksums: SharedChecksumChannel,
+    ) -> InputStream {
+        let mut recorder = InputRecorder {
             frame_number: 0,
             source,
             prev_buttons: None,
             output,
-        })
+            checksums,
+        };
+        recorder.write(&RecordingHeader {
+            magic: RECORDING_MAGIC,
+            format_version: RECORDING_FORMAT_VERSION,
+        });
+        recorder.write(metadata);
+        Box::new(recorder)
+    }
+}
+
+fn write_serialized<W: Write, T: Serialize>(output: &mut W, value: &T) -> std::io::Result<()> {
+    let mut buf = [0u8; 1024];
+    let serialized = postcard::to_slice(value, &mut buf).unwrap();
+    output.write_all(serialized)
+}
+
+impl<W: Write> InputRecorder<W> {
+    fn write<T: Serialize>(&mut self, value: &T) {
+        // Ideally we should have a separate thread that does this, to minimize latency.
+        write_serialized(&mut *self.output.borrow_mut(), value).unwrap();
     }
 }
 
@@ -30,6 +161,13 @@ impl<W: Write> Iterator for InputRecorder<W> {
     type Item = Buttons;
 
     fn next(&mut self) -> Option<Self::Item> {
+        let provided = self.checksums.borrow_mut().take_provided();
+        if let Some((frame_number, checksum)) = provided {
+            self.write(&RecordedEvent::Checksum(RecordedChecksum {
+                frame_number,
+                checksum,
+            }));
+        }
         let next = self.source.next();
         if let Some(buttons) = next {
             let did_buttons_change = match self.prev_buttons {
@@ -38,15 +176,14 @@ impl<W: Write> Iterator for InputRecorder<W> {
             };
             if did_buttons_change {
                 self.prev_buttons = Some(buttons);
-                let frame = RecordedFrame {
+                self.write(&RecordedEvent::Frame(RecordedFrame {
                     frame_number: self.frame_number,
                     buttons,
-                };
-                let mut buf = [0u8; 1024];
-                let serialized_frame = postcard::to_slice(&frame, &mut buf).unwrap();
-
-                // Ideally we should have a separate thread that does this, to minimize latency.
-                self.output.borrow_mut().write(&serialized_frame).unwrap();
+                }));
+            }
+            let mut checksums = self.checksums.borrow_mut();
+            if checksums.interval > 0 && self.frame_number.is_multiple_of(checksums.interval) {
+                checksums.request(self.frame_number);
             }
             self.frame_number += 1;
         }
@@ -57,27 +194,201 @@ impl<W: Write> Iterator for InputRecorder<W> {
 pub struct InputPlayer {
     frames: Vec<RecordedFrame>,
     frames_index: usize,
+    expected_checksums: Vec<RecordedChecksum>,
+    expected_checksums_index: usize,
+    pending_checksum: Option<RecordedChecksum>,
+    checksums: SharedChecksumChannel,
+    last_frame_number: Option<u64>,
     frame_number: u64,
     latest_buttons: Buttons,
 }
 
+fn take_from_bytes<'a, T: Deserialize<'a>>(input: &'a [u8]) -> Result<(T, &'a [u8])> {
+    postcard::take_from_bytes::<T>(input).map_err(|e| {
+        anyhow!(
+            "Unable to deserialize {}: {:?}",
+            std::any::type_name::<T>(),
+            e
+        )
+    })
+}
+
+/// The decoded contents of a recording.
+#[derive(Debug, PartialEq)]
+pub struct Recording {
+    /// This will be `None` for recordings made before we started including metadata.
+    pub metadata: Option<RecordingMetadata>,
+    pub frames: Vec<RecordedFrame>,
+    pub checksums: Vec<RecordedChecksum>,
+}
+
+impl Recording {
+    pub fn parse(input: &[u8]) -> Result<Self> {
+        let mut recording = Recording {
+            metadata: None,
+            frames: vec![],
+            checksums: vec![],
+        };
+        if input.starts_with(&RECORDING_MAGIC) {
+            let (header, mut input_remaining) = take_from_bytes::<RecordingHeader>(input)?;
+            if header.format_version > RECORDING_FORMAT_VERSION {
+                return Err(anyhow!(
+                    "Recording format version {} is newer than the latest supported version, {}.",
+                    header.format_version,
+                    RECORDING_FORMAT_VERSION
+                ));
+            }
+            if header.format_version >= 2 {
+                let (metadata, unused) = take_from_bytes::<RecordingMetadata>(input_remaining)?;
+                input_remaining = unused;
+                recording.metadata = Some(metadata);
+            }
+            while !input_remaining.is_empty() {
+                let (event, unused) = take_from_bytes::<RecordedEvent>(input_remaining)?;
+                input_remaining = unused;
+                match event {
+                    RecordedEvent::Frame(frame) => recording.frames.push(frame),
+                    RecordedEvent::Checksum(checksum) => recording.checksums.push(checksum),
+                }
+            }
+        } else {
+            let mut input_remaining = input;
+            while !input_remaining.is_empty() {
+                let (frame, unused) = take_from_bytes::<RecordedFrame>(input_remaining)?;
+                input_remaining = unused;
+                recording.frames.push(frame);
+            }
+        }
+        Ok(recording)
+    }
+
+    /// Encode the recording in the same format that `InputRecorder` uses.
+    ///
+    /// Recordings without metadata are encoded using format version 1, which
+    /// predates it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output: Vec<u8> = vec![];
+        let header = RecordingHeader {
+            magic: RECORDING_MAGIC,
+            format_version: if self.metadata.is_some() {
+                RECORDING_FORMAT_VERSION
+            } else {
+                1
+            },
+        };
+        write_serialized(&mut output, &header).unwrap();
+        if let Some(metadata) = &self.metadata {
+            write_serialized(&mut output, metadata).unwrap();
+        }
+
+        // The recorder writes each frame's checksum after the frame's buttons, so
+        // we'll do the same.
+        let mut checksums = self.checksums.iter().peekable();
+        for frame in self.frames.iter() {
+            while let Some(checksum) = checksums.next_if(|c| c.frame_number < frame.frame_number) {
+                write_serialized(&mut output, &RecordedEvent::Checksum(*checksum)).unwrap();
+            }
+            write_serialized(&mut output, &RecordedEvent::Frame(*frame)).unwrap();
+        }
+        for checksum in checksums {
+            write_serialized(&mut output, &RecordedEvent::Checksum(*checksum)).unwrap();
+        }
+        output
+    }
+
+    /// The number of the last frame that the recording has any information about,
+    /// or `None` if the recording is empty.
+    pub fn last_frame_number(&self) -> Option<u64> {
+        let last_frame = self.frames.last().map(|frame| frame.frame_number);
+        let last_checksum = self.checksums.last().map(|c| c.frame_number);
+        last_frame.max(last_checksum)
+    }
+
+    /// Iterate through the buttons held down on every frame of the recording.
+    pub fn iter_buttons(&self) -> impl Iterator<Item = Buttons> + '_ {
+        let num_frames = self.last_frame_number().map(|n| n + 1).unwrap_or(0);
+        let mut frames = self.frames.iter().peekable();
+        let mut buttons = Buttons::default();
+        (0..num_frames).map(move |frame_number| {
+            if let Some(frame) = frames.next_if(|f| f.frame_number == frame_number) {
+                buttons = frame.buttons;
+            }
+            buttons
+        })
+    }
+}
+
 impl InputPlayer {
-    pub fn new(input: Vec<u8>) -> InputStream {
-        let mut frames: Vec<RecordedFrame> = vec![];
-        let mut input_remaining: &[u8] = input.as_ref();
-        while input_remaining.len() > 0 {
-            let (frame, unused) = postcard::take_from_bytes::<RecordedFrame>(&input_remaining)
-                .expect("Unable to deserialize RecordedFrame");
-            input_remaining = unused;
-            frames.push(frame);
-        }
-        println!("Loaded {} input events.", frames.len());
-        Box::new(InputPlayer {
+    /// Play back the given encoded recording. If it contains checksums, they will be
+    /// requested from the given checksum channel and verified against the game state.
+    ///
+    /// If the recording has metadata, it's validated against the given expected
+    /// metadata, and an error is returned if playback would be meaningless.
+    pub fn new(
+        input: Vec<u8>,
+        expected_metadata: &RecordingMetadata,
+        checksums: SharedChecksumChannel,
+    ) -> Result<InputStream> {
+        Self::from_recording(Recording::parse(&input)?, expected_metadata, checksums)
+    }
+
+    /// Like `InputPlayer::new()`, but for an already-decoded recording.
+    pub fn from_recording(
+        recording: Recording,
+        expected_metadata: &RecordingMetadata,
+        checksums: SharedChecksumChannel,
+    ) -> Result<InputStream> {
+        let last_frame_number = recording.last_frame_number();
+        let Recording {
+            metadata,
+            frames,
+            checksums: expected_checksums,
+        } = recording;
+        if let Some(metadata) = metadata {
+            metadata.validate(expected_metadata)?;
+        } else {
+            println!("WARNING: Recording has no metadata, so it can't be validated.");
+        }
+        println!(
+            "Loaded {} input events and {} checksums.",
+            frames.len(),
+            expected_checksums.len()
+        );
+        Ok(Box::new(InputPlayer {
             frames,
             frames_index: 0,
+            expected_checksums,
+            expected_checksums_index: 0,
+            pending_checksum: None,
+            checksums,
+            last_frame_number,
             frame_number: 0,
             latest_buttons: Buttons::default(),
-        })
+        }))
+    }
+
+    fn verify_provided_checksum(&mut self) {
+        let mut checksums = self.checksums.borrow_mut();
+        let Some((frame_number, actual_checksum)) = checksums.take_provided() else {
+            return;
+        };
+        let Some(expected) = self.pending_checksum.take() else {
+            return;
+        };
+        assert_eq!(frame_number, expected.frame_number);
+        if expected.checksum != actual_checksum {
+            let is_first = checksums.report_desync(Desync {
+                frame_number,
+                expected_checksum: expected.checksum,
+                actual_checksum,
+            });
+            if is_first {
+                println!(
+                    "WARNING: Replay diverged from recording at frame {}.",
+                    frame_number
+                );
+            }
+        }
     }
 }
 
@@ -85,20 +396,29 @@ impl Iterator for InputPlayer {
     type Item = Buttons;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.frames_index == self.frames.len() {
-            None
-        } else {
-            let frame = &self.frames[self.frames_index];
+        self.verify_provided_checksum();
+        let last_frame_number = self.last_frame_number?;
+        if self.frame_number > last_frame_number {
+            return None;
+        }
+        if let Some(frame) = self.frames.get(self.frames_index) {
             if frame.frame_number == self.frame_number {
                 self.latest_buttons = frame.buttons;
                 self.frames_index += 1;
-                if self.frames_index == self.frames.len() {
-                    println!("Recording playback ended.");
-                }
             }
-            self.frame_number += 1;
-            Some(self.latest_buttons)
         }
+        if let Some(&checksum) = self.expected_checksums.get(self.expected_checksums_index) {
+            if checksum.frame_number == self.frame_number {
+                self.pending_checksum = Some(checksum);
+                self.expected_checksums_index += 1;
+                self.checksums.borrow_mut().request(self.frame_number);
+            }
+        }
+        if self.frame_number == last_frame_number {
+            println!("Recording playback ended.");
+        }
+        self.frame_number += 1;
+        Some(self.latest_buttons)
     }
 }
 
@@ -106,9 +426,42 @@ impl Iterator for InputPlayer {
 mod tests {
     use std::{cell::RefCell, rc::Rc};
 
-    use crate::{input::Buttons, recorder::InputPlayer};
+    use crate::{
+        checksum::{ChecksumChannel, SharedChecksumChannel},
+        input::{Buttons, InputStream},
+        recorder::InputPlayer,
+    };
+
+    use super::{InputRecorder, RecordedChecksum, RecordedFrame, Recording, RecordingMetadata};
+
+    fn test_metadata() -> RecordingMetadata {
+        RecordingMetadata {
+            game_version: "1.0.0".to_owned(),
+            start_position: "default".to_owned(),
+            fixed_fps: 60,
+            config_digest: 1,
+            world_digest: 2,
+        }
+    }
 
-    use super::InputRecorder;
+    /// Run the given input stream, providing checksums whenever they're requested,
+    /// like the game loop does.
+    fn run_with_checksums<F: Fn(u64) -> u64>(
+        stream: InputStream,
+        checksums: &SharedChecksumChannel,
+        compute_checksum: F,
+    ) -> Vec<Buttons> {
+        let mut output = vec![];
+        let mut frame_number = 0;
+        for buttons in stream {
+            output.push(buttons);
+            checksums
+                .borrow_mut()
+                .provide_if_requested(|| compute_checksum(frame_number));
+            frame_number += 1;
+        }
+        output
+    }
 
     #[test]
     fn test_it_works() {
@@ -120,12 +473,162 @@ mod tests {
         ];
 
         let recording = Rc::new(RefCell::new(vec![]));
-        let recorder = InputRecorder::new(Box::new(buttons.clone().into_iter()), recording.clone());
+        let recorder = InputRecorder::new(
+            Box::new(buttons.clone().into_iter()),
+            recording.clone(),
+            &test_metadata(),
+            Default::default(),
+        );
         let recorder_output: Vec<Buttons> = recorder.collect();
         assert_eq!(recorder_output, buttons);
 
-        let player = InputPlayer::new(recording.borrow().clone());
+        let player = InputPlayer::new(
+            recording.borrow().clone(),
+            &test_metadata(),
+            Default::default(),
+        )
+        .unwrap();
         let player_output: Vec<Buttons> = player.collect();
         assert_eq!(player_output, buttons);
     }
+
+    #[test]
+    fn test_legacy_recordings_work() {
+        let mut recording = vec![];
+        for (frame_number, buttons) in [(0, Buttons::empty()), (2, Buttons::RIGHT)] {
+            let frame = RecordedFrame {
+                frame_number,
+                buttons,
+            };
+            let mut buf = [0u8; 32];
+            recording.extend_from_slice(postcard::to_slice(&frame, &mut buf).unwrap());
+        }
+
+        let player = InputPlayer::new(recording, &test_metadata(), Default::default()).unwrap();
+        let player_output: Vec<Buttons> = player.collect();
+        assert_eq!(
+            player_output,
+            vec![Buttons::empty(), Buttons::empty(), Buttons::RIGHT]
+        );
+    }
+
+    #[test]
+    fn test_checksums_detect_first_desync() {
+        let buttons = vec![Buttons::RIGHT; 10];
+
+        let recording = Rc::new(RefCell::new(vec![]));
+        let recorder_checksums = ChecksumChannel::new_shared(3);
+        let recorder = InputRecorder::new(
+            Box::new(buttons.clone().into_iter()),
+            recording.clone(),
+            &test_metadata(),
+            recorder_checksums.clone(),
+        );
+        run_with_checksums(recorder, &recorder_checksums, |frame| frame * 10);
+
+        let player_checksums: SharedChecksumChannel = Default::default();
+        let player = InputPlayer::new(
+            recording.borrow().clone(),
+            &test_metadata(),
+            player_checksums.clone(),
+        )
+        .unwrap();
+        run_with_checksums(player, &player_checksums, |frame| frame * 10);
+        assert_eq!(player_checksums.borrow().first_desync(), None);
+
+        let player_checksums: SharedChecksumChannel = Default::default();
+        let player = InputPlayer::new(
+            recording.borrow().clone(),
+            &test_metadata(),
+            player_checksums.clone(),
+        )
+        .unwrap();
+        run_with_checksums(player, &player_checksums, |frame| {
+            if frame >= 4 {
+                frame
+            } else {
+                frame * 10
+            }
+        });
+        let desync = player_checksums.borrow().first_desync().unwrap();
+        assert_eq!(desync.frame_number, 6);
+        assert_eq!(desync.expected_checksum, 60);
+        assert_eq!(desync.actual_checksum, 6);
+    }
+
+    #[test]
+    fn test_metadata_mismatches_are_detected() {
+        let recording = Rc::new(RefCell::new(vec![]));
+        let recorder = InputRecorder::new(
+            Box::new(vec![Buttons::RIGHT].into_iter()),
+            recording.clone(),
+            &test_metadata(),
+            Default::default(),
+        );
+        recorder.for_each(drop);
+
+        let different_version = RecordingMetadata {
+            game_version: "2.0.0".to_owned(),
+            world_digest: 3,
+            ..test_metadata()
+        };
+        assert!(InputPlayer::new(
+            recording.borrow().clone(),
+            &different_version,
+            Default::default()
+        )
+        .is_ok());
+
+        let different_start_position = RecordingMetadata {
+            start_position: "boop".to_owned(),
+            ..test_metadata()
+        };
+        assert!(InputPlayer::new(
+            recording.borrow().clone(),
+            &different_start_position,
+            Default::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_recordings_round_trip() {
+        let recording = Recording {
+            metadata: Some(test_metadata()),
+            frames: vec![
+                RecordedFrame {
+                    frame_number: 0,
+                    buttons: Buttons::empty(),
+                },
+                RecordedFrame {
+                    frame_number: 3,
+                    buttons: Buttons::JUMP,
+                },
+            ],
+            checksums: vec![
+                RecordedChecksum {
+                    frame_number: 0,
+                    checksum: 123,
+                },
+                RecordedChecksum {
+                    frame_number: 5,
+                    checksum: 456,
+                },
+            ],
+        };
+        assert_eq!(Recording::parse(&recording.to_bytes()).unwrap(), recording);
+
+        let buttons: Vec<Buttons> = recording.iter_buttons().collect();
+        assert_eq!(
+            buttons,
+            vec![
+                Buttons::empty(),
+                Buttons::empty(),
+                Buttons::empty(),
+                Buttons::JUMP,
+                Buttons::JUMP,
+                Buttons::JUMP
+            ]
+        );
+    }
 }
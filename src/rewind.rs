@@ -0,0 +1,90 @@
+use std::collections::VecDeque;
+
+/// A bounded ring buffer of recent snapshots of the game state, which allows
+/// the player to rewind time.
+///
+/// Snapshots are only taken every few frames, so rewinding goes back in
+/// increments of (at least) that many frames.
+pub struct RewindBuffer<T> {
+    /// Snapshots along with the number of the frame they were taken after,
+    /// from oldest to newest.
+    snapshots: VecDeque<(u64, T)>,
+    max_snapshots: usize,
+    frames_per_snapshot: u64,
+    last_snapshot_frame: Option<u64>,
+}
+
+impl<T> RewindBuffer<T> {
+    /// Create a buffer that keeps a snapshot of every `frames_per_snapshot` frames,
+    /// going back at most `max_frames` frames.
+    pub fn new(max_frames: u64, frames_per_snapshot: u64) -> Self {
+        assert!(frames_per_snapshot > 0);
+        let max_snapshots = (max_frames / frames_per_snapshot).max(1) as usize;
+        RewindBuffer {
+            snapshots: VecDeque::with_capacity(max_snapshots),
+            max_snapshots,
+            frames_per_snapshot,
+            last_snapshot_frame: None,
+        }
+    }
+
+    /// Called after the given frame has been simulated. If enough frames have
+    /// passed since the last snapshot, the given function will be called to
+    /// create a new one, and the oldest snapshot will be forgotten if the buffer
+    /// is full.
+    pub fn maybe_record<F: FnOnce() -> T>(&mut self, frame_number: u64, create_snapshot: F) {
+        if let Some(last_snapshot_frame) = self.last_snapshot_frame {
+            if frame_number.saturating_sub(last_snapshot_frame) < self.frames_per_snapshot {
+                return;
+            }
+        }
+        if self.snapshots.len() == self.max_snapshots {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back((frame_number, create_snapshot()));
+        self.last_snapshot_frame = Some(frame_number);
+    }
+
+    /// Remove and return the most recent snapshot, if there is one. The game is
+    /// expected to resume from it.
+    pub fn rewind(&mut self) -> Option<T> {
+        let (frame_number, snapshot) = self.snapshots.pop_back()?;
+        self.last_snapshot_frame = Some(frame_number);
+        Some(snapshot)
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.last_snapshot_frame = None;
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RewindBuffer;
+
+    #[test]
+    fn test_it_works() {
+        let mut buffer = RewindBuffer::new(6, 2);
+        for frame_number in [1, 2, 3, 5, 6, 7, 9] {
+            buffer.maybe_record(frame_number, || frame_number);
+        }
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.rewind(), Some(9));
+        assert_eq!(buffer.rewind(), Some(7));
+        buffer.maybe_record(8, || 8);
+        buffer.maybe_record(9, || 9);
+        assert_eq!(buffer.rewind(), Some(9));
+        assert_eq!(buffer.rewind(), Some(5));
+        assert_eq!(buffer.rewind(), None);
+        assert!(buffer.is_empty());
+    }
+}
@@ -4,13 +4,15 @@ use macroquad::{
 };
 
 use crate::{
-    config::config,
+    config::Config,
+    context::GameContext,
     drawing::draw_rect_lines,
     entity::{filter_and_process_entities, Entity, EntityMap},
+    spatial_index::SpatialIndex,
     sprite_component::SpriteComponent,
 };
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct RouteComponent {
     pub start_point: Vec2,
     pub end_point: Vec2,
@@ -31,16 +33,18 @@ impl RouteComponent {
     }
 }
 
-pub fn route_system(entities: &mut EntityMap) {
+pub fn route_system(entities: &mut EntityMap, spatial_index: &mut SpatialIndex, ctx: &GameContext) {
     filter_and_process_entities(
         entities,
         |entity| entity.route.is_some(),
-        |entity, entities, _| {
+        |entity, entities, id| {
             let route = entity.route.as_mut().unwrap();
             if !route.is_moving {
                 return;
             }
-            if route.stop_when_blocked && is_route_blocked(&route, &entity.sprite, entities) {
+            if route.stop_when_blocked
+                && is_route_blocked(&route, &entity.sprite, entities, spatial_index, &ctx.config)
+            {
                 entity.physics.velocity = Vec2::ZERO;
                 return;
             }
@@ -53,6 +57,7 @@ pub fn route_system(entities: &mut EntityMap) {
                     entity.physics.velocity.dot(direction_to_target) > 0.;
                 if !is_moving_towards_target {
                     entity.sprite.pos = target;
+                    spatial_index.update(id, entity.sprite.bbox());
                     entity.physics.velocity = Vec2::ZERO;
                     if route.ping_pong {
                         route.is_moving_towards_start = !route.is_moving_towards_start;
@@ -69,9 +74,14 @@ fn is_route_blocked(
     route: &RouteComponent,
     sprite: &SpriteComponent,
     entities: &EntityMap,
+    spatial_index: &SpatialIndex,
+    config: &Config,
 ) -> bool {
-    if let Some(edge_bbox) = get_route_edge_bbox(route, sprite) {
-        for (_id, entity) in entities.iter() {
+    if let Some(edge_bbox) = get_route_edge_bbox(route, sprite, config) {
+        for id in spatial_index.query(&edge_bbox) {
+            let Some(entity) = entities.get(id) else {
+                continue;
+            };
             if entity.sprite.bbox().overlaps(&edge_bbox) {
                 return true;
             }
@@ -80,8 +90,12 @@ fn is_route_blocked(
     false
 }
 
-fn get_route_edge_bbox(route: &RouteComponent, sprite: &SpriteComponent) -> Option<Rect> {
-    let thickness = config().blocked_route_edge_thickness;
+fn get_route_edge_bbox(
+    route: &RouteComponent,
+    sprite: &SpriteComponent,
+    config: &Config,
+) -> Option<Rect> {
+    let thickness = config.blocked_route_edge_thickness;
     let bbox = sprite.bbox();
     let target = route.target();
     let direction_to_target = target - sprite.pos;
@@ -116,7 +130,7 @@ pub fn try_to_start_route(entity: &mut Entity, move_towards_start: bool) -> bool
     }
 }
 
-pub fn draw_route_debug_targets(entities: &EntityMap) {
+pub fn draw_route_debug_targets(entities: &EntityMap, ctx: &GameContext) {
     for (_id, entity) in entities.iter() {
         if let Some(route) = &entity.route {
             let target = route.target();
@@ -129,7 +143,7 @@ pub fn draw_route_debug_targets(entities: &EntityMap) {
                 YELLOW,
             );
             if route.stop_when_blocked {
-                if let Some(edge_bbox) = get_route_edge_bbox(&route, &entity.sprite) {
+                if let Some(edge_bbox) = get_route_edge_bbox(&route, &entity.sprite, &ctx.config) {
                     draw_rect_lines(&edge_bbox, 1., PURPLE)
                 }
             }
@@ -1,8 +1,8 @@
 use macroquad::prelude::clamp;
 
-use crate::config::config;
+use crate::config::Config;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct RunComponent {
     run_duration: f64,
     x_direction: f32,
@@ -25,8 +25,8 @@ impl RunComponent {
         time_since_last_frame: f64,
         is_pressing_left: bool,
         is_pressing_right: bool,
+        config: &Config,
     ) {
-        let config = config();
         self.x_direction = if is_pressing_left {
             -1.
         } else if is_pressing_right {
@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+
+use crate::{
+    level_runtime::{LevelRuntime, SavedLevelRuntime},
+    time::FixedGameTime,
+    world::World,
+};
+
+/// Where save slots are persisted on native builds, relative to the current
+/// working directory.
+#[cfg(not(target_arch = "wasm32"))]
+pub const SAVE_SLOTS_PATH: &str = "saves.json";
+
+/// A saved game.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SaveSlot {
+    pub level_runtime: SavedLevelRuntime,
+    /// The game time when the game was saved. This is always paused.
+    pub time: FixedGameTime,
+    /// A digest of the world that the game was saved in.
+    pub world_digest: u64,
+}
+
+impl SaveSlot {
+    pub fn new(level_runtime: &LevelRuntime, time: &FixedGameTime, world: &World) -> Self {
+        SaveSlot {
+            level_runtime: level_runtime.save(),
+            time: time.create_paused_clone(),
+            world_digest: world.digest(),
+        }
+    }
+}
+
+/// A collection of named saved games, which persist across sessions: on native
+/// builds they're stored in a file, while on the web they're stored in the
+/// browser's local storage.
+#[derive(Default, Serialize, Deserialize)]
+pub struct SaveSlots {
+    slots: BTreeMap<String, SaveSlot>,
+}
+
+impl SaveSlots {
+    /// Load the save slots from persistent storage. If nothing has been saved
+    /// yet, this will be empty.
+    pub fn load() -> Result<Self> {
+        match read_from_storage()? {
+            Some(data) => Ok(serde_json::from_str(&data)?),
+            None => Ok(SaveSlots::default()),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SaveSlot> {
+        self.slots.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.slots.keys()
+    }
+
+    /// Put the given saved game in the slot with the given name, replacing
+    /// whatever was there, and write all the slots to persistent storage.
+    pub fn save(&mut self, name: &str, slot: SaveSlot) -> Result<()> {
+        self.slots.insert(name.to_owned(), slot);
+        write_to_storage(&serde_json::to_string(self)?)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_from_storage() -> Result<Option<String>> {
+    match std::fs::read_to_string(SAVE_SLOTS_PATH) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_to_storage(data: &str) -> Result<()> {
+    Ok(std::fs::write(SAVE_SLOTS_PATH, data)?)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_from_storage() -> Result<Option<String>> {
+    Ok(crate::js_interop::js_interop_wasm32::load_save_slots())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_to_storage(data: &str) -> Result<()> {
+    crate::js_interop::js_interop_wasm32::store_save_slots(data);
+    Ok(())
+}
@@ -0,0 +1,397 @@
+use std::{collections::HashSet, rc::Rc};
+
+use anyhow::Result;
+use macroquad::{
+    prelude::{Color, WHITE},
+    shapes::draw_rectangle,
+    window::{screen_height, screen_width},
+};
+
+use crate::{
+    audio::play_sound_effects_for_events,
+    context::GameContext,
+    events::GameEvent,
+    input::InputState,
+    level_runtime::{new_game, FrameResult, LevelRuntime},
+    time::GameTime,
+    touch_controls::{are_touch_controls_enabled, draw_touch_controls},
+    world::World,
+};
+
+/// How long it takes to fade to black (or back again) when changing scenes,
+/// in seconds.
+const FADE_SECONDS: f64 = 0.5;
+
+/// How long the "level complete" message is shown for, in seconds.
+const LEVEL_COMPLETE_SECONDS: f64 = 1.5;
+
+const CREDITS: [&str; 5] = [
+    "Macroquad Fun",
+    "",
+    "Character art by LuizMelo",
+    "Made with macroquad",
+    "Thanks for playing!",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scene {
+    Title,
+    Playing,
+    Paused,
+    /// The player just reached a level they hadn't been to before.
+    LevelComplete,
+    /// The player collected every gem in the world.
+    Victory,
+    Credits,
+}
+
+/// The top-level state machine of the game, which decides what's shown on the
+/// screen and whether the simulation is running.
+pub struct SceneManager {
+    scene: Scene,
+    /// The real-world time at which the current scene started.
+    scene_start: f64,
+    /// If we're fading to black, the scene we'll change to once we're done, along
+    /// with the real-world time at which we started fading.
+    fading_to: Option<(Scene, f64)>,
+    /// The real-world time at which we started fading back in from black, if
+    /// we did.
+    fade_in_start: Option<f64>,
+    level_runtime: LevelRuntime,
+    world: Rc<World>,
+    start_position: String,
+    levels_reached: HashSet<String>,
+}
+
+impl SceneManager {
+    pub fn new(start_position: &str, world: Rc<World>, scene: Scene, now: f64) -> Self {
+        let level_runtime = new_game(start_position, world.clone());
+        let mut levels_reached = HashSet::new();
+        levels_reached.insert(level_runtime.level().identifier.clone());
+        SceneManager {
+            scene,
+            scene_start: now,
+            fading_to: None,
+            fade_in_start: None,
+            level_runtime,
+            world,
+            start_position: start_position.to_owned(),
+            levels_reached,
+        }
+    }
+
+    pub fn scene(&self) -> Scene {
+        self.scene
+    }
+
+    pub fn world(&self) -> &Rc<World> {
+        &self.world
+    }
+
+    pub fn level_runtime(&self) -> &LevelRuntime {
+        &self.level_runtime
+    }
+
+    /// Replace the current game, e.g. because a saved game was loaded.
+    pub fn set_level_runtime(&mut self, level_runtime: LevelRuntime) {
+        self.levels_reached
+            .insert(level_runtime.level().identifier.clone());
+        self.level_runtime = level_runtime;
+    }
+
+    /// Swap in a newer version of the world, keeping the main player where
+    /// they are if possible.
+    pub fn reload_world(&mut self, world: Rc<World>) -> Result<()> {
+        self.level_runtime = self.level_runtime.reload(world.clone())?;
+        self.world = world;
+        Ok(())
+    }
+
+    /// Whether the game's simulation should be running right now.
+    pub fn is_simulating(&self) -> bool {
+        self.scene == Scene::Playing && self.fading_to.is_none()
+    }
+
+    /// Fade to black, then change to the given scene and fade back in.
+    fn fade_to(&mut self, scene: Scene, now: f64) {
+        if self.fading_to.is_none() {
+            self.fading_to = Some((scene, now));
+        }
+    }
+
+    fn change_scene(&mut self, scene: Scene, now: f64) {
+        if self.scene == Scene::Credits && scene == Scene::Title {
+            self.level_runtime = new_game(&self.start_position, self.world.clone());
+            self.levels_reached.clear();
+            self.levels_reached
+                .insert(self.level_runtime.level().identifier.clone());
+        }
+        self.scene = scene;
+        self.scene_start = now;
+    }
+
+    /// Advance the game by one fixed frame. This should only be called while
+    /// `is_simulating()` is true, and it may change the current scene.
+    pub fn advance_one_frame(
+        &mut self,
+        time: &GameTime,
+        input: &InputState,
+        now: f64,
+    ) -> FrameResult {
+        let result = self.level_runtime.advance_one_frame(time, input);
+        let events = self.level_runtime.events();
+        play_sound_effects_for_events(events, &self.world);
+        if result == FrameResult::MainPlayerDied {
+            self.level_runtime = self
+                .level_runtime
+                .respawn_at_checkpoint()
+                .unwrap_or_else(|| new_game(&self.start_position, self.world.clone()));
+            return result;
+        }
+
+        let mut reached_new_level = false;
+        for event in events {
+            if let GameEvent::LevelChanged { to, .. } = event {
+                if self.levels_reached.insert(to.clone()) {
+                    reached_new_level = true;
+                }
+            }
+        }
+
+        let total_gems = self.world.total_gems(self.level_runtime.level());
+        let gems = self
+            .level_runtime
+            .entities()
+            .main_player()
+            .player
+            .map(|player| player.inventory.gems)
+            .unwrap_or(0);
+        if total_gems > 0 && gems >= total_gems {
+            self.fade_to(Scene::Victory, now);
+        } else if reached_new_level {
+            self.change_scene(Scene::LevelComplete, now);
+        }
+        result
+    }
+
+    /// Update any transitions that are in progress. This should be called once
+    /// per rendered frame.
+    pub fn update(&mut self, now: f64) {
+        if let Some((scene, start)) = self.fading_to {
+            if now - start >= FADE_SECONDS {
+                self.fading_to = None;
+                self.fade_in_start = Some(now);
+                self.change_scene(scene, now);
+            }
+        } else if self.scene == Scene::LevelComplete
+            && now - self.scene_start >= LEVEL_COMPLETE_SECONDS
+        {
+            self.change_scene(Scene::Playing, now);
+        }
+    }
+
+    /// Move on from the current scene, if it's one that waits for the player
+    /// to continue.
+    pub fn advance(&mut self, now: f64) {
+        match self.scene {
+            Scene::Title => self.fade_to(Scene::Playing, now),
+            Scene::Victory => self.fade_to(Scene::Credits, now),
+            Scene::Credits => self.fade_to(Scene::Title, now),
+            Scene::Playing | Scene::Paused | Scene::LevelComplete => {}
+        }
+    }
+
+    pub fn pause(&mut self, now: f64) {
+        if self.scene == Scene::Playing && self.fading_to.is_none() {
+            self.change_scene(Scene::Paused, now);
+        }
+    }
+
+    pub fn toggle_pause(&mut self, now: f64) {
+        match self.scene {
+            Scene::Playing => self.pause(now),
+            Scene::Paused => self.change_scene(Scene::Playing, now),
+            _ => {}
+        }
+    }
+
+    pub fn draw(&self, now: f64, is_browser: bool) {
+        if self.scene != Scene::Credits {
+            self.level_runtime.draw();
+        }
+
+        let ctx = self.world.ctx();
+        let is_touch = are_touch_controls_enabled();
+        match self.scene {
+            Scene::Title => {
+                draw_overlay(0.75);
+                draw_lines(
+                    &[
+                        "Macroquad Fun",
+                        "",
+                        if is_touch {
+                            "Tap to start"
+                        } else {
+                            "Press Enter to start"
+                        },
+                    ],
+                    ctx,
+                );
+            }
+            Scene::Playing => {
+                draw_touch_controls(ctx);
+            }
+            Scene::Paused => {
+                draw_overlay(0.5);
+                draw_lines(
+                    &[if is_touch {
+                        "Game paused (tap to unpause)"
+                    } else if is_browser {
+                        "Game paused (press ESC to unpause)"
+                    } else {
+                        "Game paused (press Q to quit)"
+                    }],
+                    ctx,
+                );
+            }
+            Scene::LevelComplete => {
+                draw_lines(&["Level complete!"], ctx);
+            }
+            Scene::Victory => {
+                draw_overlay(0.5);
+                draw_lines(
+                    &[
+                        "You collected all the gems!",
+                        "",
+                        if is_touch {
+                            "Tap to continue"
+                        } else {
+                            "Press Enter to continue"
+                        },
+                    ],
+                    ctx,
+                );
+            }
+            Scene::Credits => {
+                draw_overlay(1.);
+                draw_lines(&CREDITS, ctx);
+            }
+        }
+
+        let fade_amount = if let Some((_, start)) = self.fading_to {
+            (now - start) / FADE_SECONDS
+        } else if let Some(start) = self.fade_in_start {
+            1. - (now - start) / FADE_SECONDS
+        } else {
+            0.
+        };
+        if fade_amount > 0. {
+            draw_overlay(fade_amount.min(1.) as f32);
+        }
+    }
+}
+
+/// Cover the whole screen with black at the given opacity.
+fn draw_overlay(alpha: f32) {
+    draw_rectangle(
+        0.,
+        0.,
+        screen_width(),
+        screen_height(),
+        Color::new(0., 0., 0., alpha),
+    );
+}
+
+/// Draw the given lines of text centered on the screen.
+fn draw_lines(lines: &[&str], ctx: &GameContext) {
+    let font = &ctx.assets.font;
+    let line_height = font.char_height as f32 * ctx.config.sprite_scale * 2.;
+    let top = (screen_height() - line_height * (lines.len() - 1) as f32) / 2.;
+    for (i, line) in lines.iter().enumerate() {
+        font.draw_centered_text(
+            line,
+            screen_width() / 2.,
+            top + line_height * i as f32,
+            WHITE,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        headless::load_headless,
+        input::{Buttons, InputState},
+        level_runtime::new_game,
+        time::FixedGameTime,
+        CONFIG_PATH, WORLD_PATH,
+    };
+
+    use super::{Scene, SceneManager, FADE_SECONDS, LEVEL_COMPLETE_SECONDS};
+
+    const START_POSITION: &str = "dev_platform";
+
+    fn make_scene_manager(scene: Scene) -> SceneManager {
+        let world = load_headless(CONFIG_PATH, WORLD_PATH).unwrap();
+        SceneManager::new(START_POSITION, world, scene, 0.)
+    }
+
+    #[test]
+    fn test_scenes_change_once_faded_out() {
+        let mut manager = make_scene_manager(Scene::Title);
+        manager.advance(1.);
+        manager.update(1. + FADE_SECONDS * 0.9);
+        assert_eq!(manager.scene(), Scene::Title);
+        assert!(!manager.is_simulating());
+        manager.update(1. + FADE_SECONDS);
+        assert_eq!(manager.scene(), Scene::Playing);
+        assert!(manager.is_simulating());
+    }
+
+    #[test]
+    fn test_level_complete_times_out() {
+        let mut manager = make_scene_manager(Scene::Playing);
+        manager.change_scene(Scene::LevelComplete, 1.);
+        manager.update(1. + LEVEL_COMPLETE_SECONDS * 0.9);
+        assert_eq!(manager.scene(), Scene::LevelComplete);
+        assert!(!manager.is_simulating());
+        manager.update(1. + LEVEL_COMPLETE_SECONDS);
+        assert_eq!(manager.scene(), Scene::Playing);
+        assert!(manager.is_simulating());
+    }
+
+    #[test]
+    fn test_leaving_credits_resets_game() {
+        let mut manager = make_scene_manager(Scene::Credits);
+        let start_level = manager.level_runtime().level().identifier.clone();
+        let other_game = new_game("dev", manager.world().clone());
+        assert_ne!(other_game.level().identifier, start_level);
+        manager.set_level_runtime(other_game);
+        manager.advance(1.);
+        manager.update(1. + FADE_SECONDS);
+        assert_eq!(manager.scene(), Scene::Title);
+        assert_eq!(manager.level_runtime().level().identifier, start_level);
+        assert_eq!(manager.levels_reached.len(), 1);
+    }
+
+    #[test]
+    fn test_collecting_every_reachable_gem_wins_the_game() {
+        // The dev levels are separate from the main game and only have one gem.
+        let world = load_headless(CONFIG_PATH, WORLD_PATH).unwrap();
+        let mut fixed_time = FixedGameTime::new(&world.ctx().config, 0.);
+        let mut input = InputState::default();
+        let mut manager = SceneManager::new("dev", world, Scene::Playing, 0.);
+        for _ in 0..60 {
+            if !manager.is_simulating() {
+                break;
+            }
+            input.update(Buttons::RIGHT | Buttons::JUMP);
+            let time = fixed_time.force_next_fixed_frame();
+            manager.advance_one_frame(&time, &input, 1.);
+        }
+        assert_eq!(manager.scene(), Scene::Playing);
+        assert!(!manager.is_simulating());
+        manager.update(1. + FADE_SECONDS);
+        assert_eq!(manager.scene(), Scene::Victory);
+    }
+}
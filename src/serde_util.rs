@@ -0,0 +1,44 @@
+//! Helpers for serializing types that don't implement serde's traits themselves.
+
+use macroquad::prelude::{Color, Rect};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Rect")]
+pub struct RectDef {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Color")]
+pub struct ColorDef {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+/// A `Color` that can be serialized, for use in compound types.
+#[derive(Serialize, Deserialize)]
+pub struct SerializableColor(#[serde(with = "ColorDef")] pub Color);
+
+pub mod option_color {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Color>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.map(SerializableColor).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Color>, D::Error> {
+        let value: Option<SerializableColor> = Deserialize::deserialize(deserializer)?;
+        Ok(value.map(|color| color.0))
+    }
+}
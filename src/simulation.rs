@@ -0,0 +1,96 @@
+use std::rc::Rc;
+
+use crate::{
+    checksum::SharedChecksumChannel,
+    input::{Buttons, InputState, InputStream},
+    level_runtime::{new_game, FrameResult, LevelRuntime},
+    outcomes::SessionOutcomes,
+    time::FixedGameTime,
+    world::World,
+};
+
+/// Runs the game's fixed-timestep simulation as fast as possible, without
+/// tying it to real-world time.
+pub struct Simulation {
+    world: Rc<World>,
+    start_position: String,
+    level_runtime: LevelRuntime,
+    fixed_time: FixedGameTime,
+    input_state: InputState,
+    checksums: SharedChecksumChannel,
+    outcomes: SessionOutcomes,
+    deaths: u32,
+}
+
+impl Simulation {
+    pub fn new(start_position: &str, world: Rc<World>) -> Self {
+        Simulation {
+            level_runtime: new_game(start_position, world.clone()),
+            fixed_time: FixedGameTime::new(&world.ctx().config, 0.),
+            world,
+            start_position: start_position.to_owned(),
+            input_state: InputState::default(),
+            checksums: Default::default(),
+            outcomes: Default::default(),
+            deaths: 0,
+        }
+    }
+
+    /// Provide checksums of the game state through the given channel whenever
+    /// they're requested, e.g. by an `InputPlayer` verifying a recording.
+    pub fn with_checksums(mut self, checksums: SharedChecksumChannel) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Advance the simulation by one fixed frame, with the given buttons held down.
+    ///
+    /// If the main player dies, they respawn at the last checkpoint they reached
+    /// (or the game is restarted if there isn't one), just like in the windowed
+    /// version of the game.
+    pub fn advance_one_frame(&mut self, buttons: Buttons) -> FrameResult {
+        self.input_state.update(buttons);
+        let time = self.fixed_time.force_next_fixed_frame();
+        let result = self
+            .level_runtime
+            .advance_one_frame(&time, &self.input_state);
+        self.outcomes.update(&self.level_runtime, &self.world);
+        if result == FrameResult::MainPlayerDied {
+            self.deaths += 1;
+            self.level_runtime = self
+                .level_runtime
+                .respawn_at_checkpoint()
+                .unwrap_or_else(|| new_game(&self.start_position, self.world.clone()));
+        }
+        self.checksums
+            .borrow_mut()
+            .provide_if_requested(|| self.level_runtime.checksum());
+        result
+    }
+
+    /// Advance the simulation until the given input stream runs out.
+    pub fn run(&mut self, input_stream: InputStream) {
+        for buttons in input_stream {
+            self.advance_one_frame(buttons);
+        }
+    }
+
+    pub fn level_runtime(&self) -> &LevelRuntime {
+        &self.level_runtime
+    }
+
+    /// How many fixed frames have been simulated so far.
+    pub fn frames_so_far(&self) -> u64 {
+        self.fixed_time.frames_so_far()
+    }
+
+    /// What the player has accomplished so far.
+    pub fn outcomes(&self) -> &SessionOutcomes {
+        &self.outcomes
+    }
+
+    /// How many times the main player has died so far.
+    pub fn deaths(&self) -> u32 {
+        self.deaths
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use macroquad::prelude::Rect;
+
+use crate::entity::EntityMap;
+
+/// The width and height of each cell in the grid, in pixels.
+const CELL_SIZE: f32 = 128.;
+
+/// The cells that a rectangle touches, as inclusive (left, top, right, bottom)
+/// cell coordinates.
+type CellRange = (i32, i32, i32, i32);
+
+fn cell_range(rect: &Rect) -> CellRange {
+    (
+        (rect.left() / CELL_SIZE).floor() as i32,
+        (rect.top() / CELL_SIZE).floor() as i32,
+        (rect.right() / CELL_SIZE).floor() as i32,
+        (rect.bottom() / CELL_SIZE).floor() as i32,
+    )
+}
+
+/// A uniform grid that makes it quick to find everything overlapping a given
+/// rectangle, without having to check every single entity (or collider) in
+/// the level.
+///
+/// The index only knows about the rectangles it's been given, so whoever owns it
+/// needs to `update()` it whenever something moves.
+#[derive(Default, Clone)]
+pub struct SpatialIndex {
+    cells: HashMap<(i32, i32), Vec<u64>>,
+    rects: HashMap<u64, Rect>,
+}
+
+impl SpatialIndex {
+    /// Replace everything in the index with the current bounding boxes of the
+    /// given entities.
+    pub fn rebuild(&mut self, entities: &EntityMap) {
+        // Clear the cells rather than removing them, so we can reuse their
+        // allocations.
+        for ids in self.cells.values_mut() {
+            ids.clear();
+        }
+        self.rects.clear();
+        for (id, entity) in entities.iter() {
+            self.insert(id, entity.sprite.bbox());
+        }
+    }
+
+    fn insert(&mut self, id: u64, rect: Rect) {
+        let (left, top, right, bottom) = cell_range(&rect);
+        for y in top..=bottom {
+            for x in left..=right {
+                self.cells.entry((x, y)).or_default().push(id);
+            }
+        }
+        self.rects.insert(id, rect);
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        let Some(rect) = self.rects.remove(&id) else {
+            return;
+        };
+        let (left, top, right, bottom) = cell_range(&rect);
+        for y in top..=bottom {
+            for x in left..=right {
+                if let Some(ids) = self.cells.get_mut(&(x, y)) {
+                    ids.retain(|&other_id| other_id != id);
+                }
+            }
+        }
+    }
+
+    /// Set the rectangle for the given id, adding it to the index if it's not
+    /// already there.
+    pub fn update(&mut self, id: u64, rect: Rect) {
+        if let Some(old_rect) = self.rects.get_mut(&id) {
+            if cell_range(old_rect) == cell_range(&rect) {
+                // This is the common case, since most things only move a
+                // few pixels per frame.
+                *old_rect = rect;
+                return;
+            }
+        }
+        self.remove(id);
+        self.insert(id, rect);
+    }
+
+    /// Returns the ids of everything whose rectangle overlaps the given one, in
+    /// ascending order.
+    pub fn query(&self, rect: &Rect) -> Vec<u64> {
+        let mut result = vec![];
+        let (left, top, right, bottom) = cell_range(rect);
+        for y in top..=bottom {
+            for x in left..=right {
+                let Some(ids) = self.cells.get(&(x, y)) else {
+                    continue;
+                };
+                result.extend(
+                    ids.iter()
+                        .copied()
+                        .filter(|id| self.rects[id].overlaps(rect)),
+                );
+            }
+        }
+        result.sort_unstable();
+        result.dedup();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::prelude::Rect;
+
+    use super::{SpatialIndex, CELL_SIZE};
+
+    #[test]
+    fn query_returns_overlapping_ids_in_order() {
+        let mut index = SpatialIndex::default();
+        index.update(3, Rect::new(0., 0., 10., 10.));
+        index.update(1, Rect::new(CELL_SIZE - 5., 0., 10., 10.));
+        index.update(2, Rect::new(CELL_SIZE * 5., 0., 10., 10.));
+        assert_eq!(index.query(&Rect::new(0., 0., CELL_SIZE, 10.)), vec![1, 3]);
+    }
+
+    #[test]
+    fn moved_and_removed_ids_are_reflected_in_queries() {
+        let mut index = SpatialIndex::default();
+        let far_away = Rect::new(CELL_SIZE * 10., CELL_SIZE * 10., 10., 10.);
+        index.update(1, Rect::new(0., 0., 10., 10.));
+        index.update(1, far_away);
+        index.update(2, far_away);
+        index.remove(2);
+        assert!(index.query(&Rect::new(0., 0., 10., 10.)).is_empty());
+        assert_eq!(index.query(&far_away), vec![1]);
+    }
+}
@@ -4,30 +4,32 @@ use macroquad::{
 };
 
 use crate::{
+    context::GameContext,
     drawing::draw_rect_lines,
     level::Level,
     materials::MaterialRenderer,
-    sprite_renderer::{SpriteDrawParams, SpriteRenderer},
+    serde_util::{option_color, RectDef},
+    sprite_renderer::{SpriteDrawParams, SpriteHandle},
     time::GameTime,
 };
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub enum Rotation {
     #[default]
     None,
     Clockwise270,
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 pub enum Renderer {
     #[default]
     Sprite,
     Invisible,
-    SolidRectangle(Rect),
-    EntityTiles(Rect),
+    SolidRectangle(#[serde(with = "RectDef")] Rect),
+    EntityTiles(#[serde(with = "RectDef")] Rect),
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 pub struct SpriteComponent {
     pub pos: Vec2,
 
@@ -37,6 +39,7 @@ pub struct SpriteComponent {
     ///
     /// This is only public for ease of construction. Prefer
     /// `relative_bbox()` to this.
+    #[serde(with = "RectDef")]
     pub base_relative_bbox: Rect,
 
     pub rotation: Rotation,
@@ -46,12 +49,13 @@ pub struct SpriteComponent {
     /// [Renderer::Sprite]. If it's not, the dimensions of this sprite
     /// can still be used in the computation of bounding boxes and such
     /// (this can be useful for entities that are children of parents).
-    pub sprite: Option<&'static SpriteRenderer>,
+    pub sprite: Option<SpriteHandle>,
 
     pub material: MaterialRenderer,
 
     /// The tint of the sprite, or the fill color of the shape, depending
     /// on which renderer is active.
+    #[serde(with = "option_color")]
     pub color: Option<Color>,
 
     pub is_facing_left: bool,
@@ -74,7 +78,7 @@ pub struct SpriteComponent {
     pub current_frame_number: u32,
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 /// Rendering/bounding box behavior of a sprite component when it's facing
 /// left (by default, we assume it's facing right).
 ///
@@ -90,7 +94,7 @@ pub enum LeftFacingRendering {
 }
 
 impl SpriteComponent {
-    fn get_sprite_dimensions(&self, sprite: &SpriteRenderer) -> Vec2 {
+    fn get_sprite_dimensions(&self, sprite: &SpriteHandle) -> Vec2 {
         match self.rotation {
             Rotation::None => Vec2::new(sprite.frame_width(), sprite.frame_height()),
             Rotation::Clockwise270 => Vec2::new(sprite.frame_height(), sprite.frame_width()),
@@ -169,7 +173,7 @@ impl SpriteComponent {
         }
     }
 
-    fn get_sprite_draw_coords(&self, sprite: &SpriteRenderer) -> Vec2 {
+    fn get_sprite_draw_coords(&self, sprite: &SpriteHandle) -> Vec2 {
         let mut x = self.pos.x;
         let y = self.pos.y;
 
@@ -195,14 +199,14 @@ impl SpriteComponent {
         }
     }
 
-    pub fn draw_current_frame(&self, level: &Level) {
-        self.material.start_using();
+    pub fn draw_current_frame(&self, level: &Level, ctx: &GameContext) {
+        self.material.start_using(&ctx.assets);
         match self.renderer {
             Renderer::Invisible => {}
             Renderer::Sprite => {
                 if let Some(sprite) = self.sprite {
-                    let pos = self.get_sprite_draw_coords(sprite);
-                    sprite.draw_ex(
+                    let pos = self.get_sprite_draw_coords(&sprite);
+                    ctx.assets.sprite_renderer(sprite.id).draw_ex(
                         pos.x,
                         pos.y,
                         self.current_frame_number,
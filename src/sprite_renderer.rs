@@ -1,8 +1,10 @@
 use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::config::config;
+use crate::game_assets::SpriteRendererId;
 
 pub struct SpriteRenderer {
+    id: SpriteRendererId,
     texture: Texture2D,
     scale: f32,
     frame_size: Vec2,
@@ -33,13 +35,39 @@ impl Default for SpriteDrawParams {
 }
 
 impl SpriteRenderer {
-    pub fn new(texture: Texture2D, num_frames: u32) -> Self {
+    pub fn new(id: SpriteRendererId, texture: Texture2D, num_frames: u32, scale: f32) -> Self {
         texture.set_filter(FilterMode::Nearest);
         SpriteRenderer {
+            id,
             texture,
             num_frames,
             frame_size: Vec2::new(texture.width() / num_frames as f32, texture.height()),
-            scale: config().sprite_scale,
+            scale,
+        }
+    }
+
+    /// Create a sprite renderer that can't actually draw anything, but
+    /// which still knows its frame dimensions (based on the given image).
+    /// Used when running headless.
+    pub fn new_headless(id: SpriteRendererId, image: &Image, num_frames: u32, scale: f32) -> Self {
+        SpriteRenderer {
+            id,
+            texture: Texture2D::empty(),
+            num_frames,
+            frame_size: Vec2::new(
+                image.width() as f32 / num_frames as f32,
+                image.height() as f32,
+            ),
+            scale,
+        }
+    }
+
+    /// A handle that entities can use to refer to this sprite.
+    pub fn handle(&self) -> SpriteHandle {
+        SpriteHandle {
+            id: self.id,
+            frame_size: Vec2::new(self.frame_width(), self.frame_height()),
+            num_frames: self.num_frames,
         }
     }
 
@@ -90,3 +118,35 @@ impl SpriteRenderer {
         self.draw_ex(x, y, frame_number, Default::default())
     }
 }
+
+/// Refers to a sprite renderer in the game assets, along with the dimensions of
+/// its frames, which are needed to compute bounding boxes even when the assets
+/// aren't at hand.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpriteHandle {
+    pub id: SpriteRendererId,
+    frame_size: Vec2,
+    num_frames: u32,
+}
+
+impl SpriteHandle {
+    pub fn frame_width(&self) -> f32 {
+        self.frame_size.x
+    }
+
+    pub fn frame_height(&self) -> f32 {
+        self.frame_size.y
+    }
+
+    pub fn frame_rect(&self) -> Rect {
+        Rect::new(0., 0., self.frame_width(), self.frame_height())
+    }
+
+    pub fn num_frames(&self) -> u32 {
+        self.num_frames
+    }
+
+    pub fn last_frame(&self) -> u32 {
+        self.num_frames - 1
+    }
+}
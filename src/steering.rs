@@ -1,6 +1,6 @@
 use crate::entity::EntityMap;
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 pub struct SteeringComponent {
     pub x_direction: i8,
 }
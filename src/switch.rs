@@ -1,33 +1,41 @@
 use crate::{
-    audio::{play_sound_effect, play_sound_effect_at_volume},
     entity::{filter_and_process_entities, EntityMap},
-    game_assets::game_assets,
-    level::{EntityKind, Level},
+    events::GameEvent,
+    level::Level,
     route::try_to_start_route,
+    spatial_index::SpatialIndex,
 };
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum TriggerType {
     ToggleRoute,
     Destroy,
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 pub struct SwitchComponent {
     pub is_switched_on: bool,
     pub trigger: Option<(TriggerType, u64)>,
     pub has_been_switched_on: bool,
 }
 
-pub fn switch_system(entities: &mut EntityMap, level: &Level) {
+pub fn switch_system(
+    entities: &mut EntityMap,
+    spatial_index: &mut SpatialIndex,
+    events: &mut Vec<GameEvent>,
+    level: &Level,
+) {
     filter_and_process_entities(
         entities,
         |entity| entity.switch.is_some(),
-        |switch_entity, entities, _| {
+        |switch_entity, entities, switch_id| {
             let switch_bbox = &switch_entity.sprite.bbox();
             let mut switch = switch_entity.switch.as_mut().unwrap();
             let mut overlaps_anything = false;
-            for (_id, entity) in entities.iter() {
+            for id in spatial_index.query(switch_bbox) {
+                let Some(entity) = entities.get(id) else {
+                    continue;
+                };
                 if (switch_entity.physics.collision_flags & entity.physics.collision_flags)
                     .is_empty()
                 {
@@ -49,37 +57,38 @@ pub fn switch_system(entities: &mut EntityMap, level: &Level) {
                     switch.has_been_switched_on = true;
                 }
 
-                // Now see if our corresponding entity in the level data wants us
-                // to trigger anything.
-                if let Some(iid) = &switch_entity.iid {
-                    if let Some(entity) = level.entities.get(iid) {
-                        if let EntityKind::Trigger(args) = &entity.kind {
-                            if let Some((sound, volume)) = args.play_sound_effect {
-                                if switch.is_switched_on && !has_been_switched_on_before {
-                                    play_sound_effect_at_volume(sound, volume);
-                                }
-                            }
-                        }
-                    };
-                };
-
                 // Now look at our on-entity data and see if we need to trigger anything.
-                let Some((trigger_type, id)) = switch.trigger else {
-                    return
-                };
-                let Some(triggered_entity) = entities.get_mut(id) else {
-                    return
-                };
-                match trigger_type {
-                    TriggerType::Destroy => {
-                        entities.remove(id);
-                    }
-                    TriggerType::ToggleRoute => {
-                        if try_to_start_route(triggered_entity, !switch.is_switched_on) {
-                            play_sound_effect(game_assets().switch_sound);
+                let mut started_route = false;
+                let mut destroyed = None;
+                if let Some((trigger_type, id)) = switch.trigger {
+                    if let Some(triggered_entity) = entities.get_mut(id) {
+                        match trigger_type {
+                            TriggerType::Destroy => {
+                                destroyed = entities.remove(id).map(|entity| (id, entity.iid));
+                                spatial_index.remove(id);
+                            }
+                            TriggerType::ToggleRoute => {
+                                started_route =
+                                    try_to_start_route(triggered_entity, !switch.is_switched_on);
+                            }
                         }
                     }
                 }
+
+                // Anything that our corresponding entity in the level data wants us
+                // to trigger, like playing a sound effect, is up to whoever handles
+                // this event.
+                events.push(GameEvent::SwitchToggled {
+                    id: switch_id,
+                    iid: switch_entity.iid,
+                    level: level.identifier.clone(),
+                    is_switched_on: switch.is_switched_on,
+                    is_first_time_on: switch.is_switched_on && !has_been_switched_on_before,
+                    started_route,
+                });
+                if let Some((id, iid)) = destroyed {
+                    events.push(GameEvent::EntityDestroyed { id, iid });
+                }
             }
         },
     );
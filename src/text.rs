@@ -1,16 +1,15 @@
 use macroquad::prelude::{Rect, WHITE};
 
 use crate::{
-    config::config,
+    context::GameContext,
     entity::{Entity, EntityMap},
-    game_assets::game_assets,
     level::Level,
     physics::PhysicsComponent,
     sprite_component::{Renderer, SpriteComponent},
     time::GameTime,
 };
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub struct TextComponent {
     max_chars: u16,
     last_max_chars_change_frame_number: u64,
@@ -36,8 +35,8 @@ pub fn create_text_entity(rect: Rect) -> Entity {
     }
 }
 
-pub fn update_level_text(entities: &mut EntityMap, time: &GameTime) {
-    let absolute_frame_number = (time.now * 1000.0 / config().ms_per_text_char) as u64;
+pub fn update_level_text(entities: &mut EntityMap, time: &GameTime, ctx: &GameContext) {
+    let absolute_frame_number = (time.now * 1000.0 / ctx.config.ms_per_text_char) as u64;
     let player_bbox = entities.main_player().sprite.bbox();
     for (_, entity) in entities.iter_mut() {
         let Some(text) = entity.text.as_mut() else {
@@ -58,7 +57,7 @@ pub fn update_level_text(entities: &mut EntityMap, time: &GameTime) {
     }
 }
 
-pub fn draw_level_text(entities: &EntityMap, level: &Level) {
+pub fn draw_level_text(entities: &EntityMap, level: &Level, ctx: &GameContext) {
     for (_, entity) in entities.iter() {
         let Some(text) = entity.text else {
             continue
@@ -75,9 +74,9 @@ pub fn draw_level_text(entities: &EntityMap, level: &Level) {
             continue
         };
 
-        let font = &game_assets().font;
+        let font = &ctx.assets.font;
         let mut y = 128.;
-        let line_height = (font.char_height as f32 + 2.) * config().sprite_scale;
+        let line_height = (font.char_height as f32 + 2.) * ctx.config.sprite_scale;
         let mut chars_left = text.max_chars as usize;
         for line in lines {
             let end_index = if line.len() > chars_left {
@@ -1,6 +1,6 @@
 use std::iter::Fuse;
 
-use crate::{config::config, sprite_renderer::SpriteRenderer};
+use crate::{config::Config, sprite_renderer::SpriteHandle};
 
 const DEFAULT_MAX_TIME_BETWEEN_FRAMES: f64 = 1. / 30.;
 
@@ -24,12 +24,12 @@ pub struct GameTime {
 impl GameTime {
     /// This is a convenience method for animating sprites in a loop (e.g. a running
     /// animation).
-    pub fn looping_frame_number(&self, sprite: &SpriteRenderer) -> u32 {
+    pub fn looping_frame_number(&self, sprite: &SpriteHandle) -> u32 {
         (self.absolute_frame_number % sprite.num_frames() as u64) as u32
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct FixedGameTime {
     /// The current game time, in seconds, taking into account `excess_time_offset`.
     /// This means that e.g. if the game was paused for 3 seconds, this will not
@@ -75,12 +75,12 @@ pub struct FixedGameTime {
 }
 
 impl FixedGameTime {
-    pub fn new(fixed_frame_rate: u64, now: f64) -> Self {
+    pub fn new(config: &Config, now: f64) -> Self {
         Self::new_ex(
-            fixed_frame_rate,
+            config.fixed_fps,
             now,
             DEFAULT_MAX_TIME_BETWEEN_FRAMES,
-            config().ms_per_animation_frame,
+            config.ms_per_animation_frame,
         )
     }
 
@@ -161,19 +161,33 @@ impl FixedGameTime {
         let time_passed = self.now - self.start;
         let total_frames = (time_passed / self.frame_duration) as u64;
         if total_frames > self.frames_so_far {
-            self.frames_so_far += 1;
-            let synthetic_now = self.start + (self.frames_so_far as f64) * self.frame_duration;
-            Some(GameTime {
-                now: synthetic_now,
-                absolute_frame_number: (synthetic_now * 1000.0 / self.ms_per_animation_frame)
-                    as u64,
-                time_since_last_frame: self.frame_duration,
-            })
+            Some(self.force_next_fixed_frame())
         } else {
             None
         }
     }
 
+    /// Immediately advance to the next fixed frame, regardless of how much
+    /// time has actually passed. This is useful for running the simulation as
+    /// fast as possible, e.g. when we're running headless.
+    pub fn force_next_fixed_frame(&mut self) -> GameTime {
+        self.frames_so_far += 1;
+        let synthetic_now = self.start + (self.frames_so_far as f64) * self.frame_duration;
+        if synthetic_now > self.now {
+            self.now = synthetic_now;
+        }
+        GameTime {
+            now: synthetic_now,
+            absolute_frame_number: (synthetic_now * 1000.0 / self.ms_per_animation_frame) as u64,
+            time_since_last_frame: self.frame_duration,
+        }
+    }
+
+    /// How many fixed frames we've processed so far.
+    pub fn frames_so_far(&self) -> u64 {
+        self.frames_so_far
+    }
+
     pub fn iter_fixed_frames<'a>(&'a mut self) -> Fuse<FixedFrameIterator<'a>> {
         (FixedFrameIterator { time: self }).fuse()
     }
@@ -254,4 +268,20 @@ mod tests {
             }]
         );
     }
+
+    #[test]
+    fn test_force_next_fixed_frame_works() {
+        let mut fixed = FixedGameTime::new_ex(1, 0., 2., 100.);
+        assert_eq!(
+            fixed.force_next_fixed_frame(),
+            GameTime {
+                now: 1.0,
+                absolute_frame_number: 10,
+                time_since_last_frame: 1.0
+            }
+        );
+        assert_eq!(fixed.frames_so_far(), 1);
+        fixed.update(1.5);
+        assert_eq!(get_frames(&mut fixed), vec![]);
+    }
 }
@@ -0,0 +1,160 @@
+use macroquad::{
+    input::{touches, TouchPhase},
+    prelude::{vec2, Color, Rect, Vec2},
+    shapes::draw_rectangle,
+    window::{screen_height, screen_width},
+};
+
+use crate::{context::GameContext, input::Buttons};
+
+/// How big each touch zone is, as a fraction of the screen's height.
+const ZONE_SIZE: f32 = 0.25;
+
+const ZONE_COLOR: Color = Color::new(1., 1., 1., 0.2);
+
+const LABEL_COLOR: Color = Color::new(1., 1., 1., 0.6);
+
+/// Whether the on-screen touch controls should be used. Currently this is only
+/// the case on the web, when the browser says it's running on a touch device.
+pub fn are_touch_controls_enabled() -> bool {
+    #[cfg(target_arch = "wasm32")]
+    {
+        crate::js_interop::js_interop_wasm32::has_touch_screen()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        false
+    }
+}
+
+/// The on-screen zones that can be touched, along with the buttons they
+/// correspond to and the labels drawn on them, for a screen of the given size.
+fn zones(screen_size: Vec2) -> [(Rect, Buttons, &'static str); 3] {
+    let size = screen_size.y * ZONE_SIZE;
+    let margin = size / 4.;
+    let y = screen_size.y - size - margin;
+    [
+        (Rect::new(margin, y, size, size), Buttons::LEFT, "<"),
+        (
+            Rect::new(margin * 2. + size, y, size, size),
+            Buttons::RIGHT,
+            ">",
+        ),
+        (
+            Rect::new(screen_size.x - size - margin, y, size, size),
+            Buttons::JUMP,
+            "^",
+        ),
+    ]
+}
+
+fn screen_size() -> Vec2 {
+    vec2(screen_width(), screen_height())
+}
+
+/// The buttons held down by touches at the given positions, on a screen of
+/// the given size.
+fn buttons_at_positions(positions: impl IntoIterator<Item = Vec2>, screen_size: Vec2) -> Buttons {
+    let zones = zones(screen_size);
+    let mut buttons = Buttons::empty();
+    for position in positions {
+        for (rect, button, _) in zones.iter() {
+            if rect.contains(position) {
+                buttons |= *button;
+            }
+        }
+    }
+    buttons
+}
+
+/// The buttons being held down via the on-screen touch controls.
+pub fn touch_buttons() -> Buttons {
+    if !are_touch_controls_enabled() {
+        return Buttons::empty();
+    }
+    let positions = touches()
+        .into_iter()
+        .filter(|touch| !matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled))
+        .map(|touch| touch.position);
+    buttons_at_positions(positions, screen_size())
+}
+
+/// Whether the player started touching the screen this frame. This can be
+/// used as an alternative to pressing a key on devices without keyboards.
+pub fn was_screen_tapped() -> bool {
+    are_touch_controls_enabled()
+        && touches()
+            .iter()
+            .any(|touch| touch.phase == TouchPhase::Started)
+}
+
+pub fn draw_touch_controls(ctx: &GameContext) {
+    if !are_touch_controls_enabled() {
+        return;
+    }
+    let font = &ctx.assets.font;
+    for (rect, _, label) in zones(screen_size()) {
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, ZONE_COLOR);
+        font.draw_centered_text(label, rect.center().x, rect.center().y, LABEL_COLOR);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::prelude::{vec2, Vec2};
+
+    use crate::input::Buttons;
+
+    use super::buttons_at_positions;
+
+    const SCREEN_SIZE: Vec2 = Vec2::new(800., 400.);
+
+    // With a 400px tall screen, each zone is 100px square with a 25px margin,
+    // so the zones span y = 275..375 and are centered at these positions.
+    const LEFT: Vec2 = Vec2::new(75., 325.);
+    const RIGHT: Vec2 = Vec2::new(200., 325.);
+    const JUMP: Vec2 = Vec2::new(725., 325.);
+
+    #[test]
+    fn test_no_touches_press_nothing() {
+        assert_eq!(buttons_at_positions([], SCREEN_SIZE), Buttons::empty());
+    }
+
+    #[test]
+    fn test_touches_outside_zones_press_nothing() {
+        let positions = [vec2(400., 100.), vec2(400., 325.), vec2(75., 200.)];
+        assert_eq!(
+            buttons_at_positions(positions, SCREEN_SIZE),
+            Buttons::empty()
+        );
+    }
+
+    #[test]
+    fn test_single_touches_press_their_zone_button() {
+        assert_eq!(buttons_at_positions([LEFT], SCREEN_SIZE), Buttons::LEFT);
+        assert_eq!(buttons_at_positions([RIGHT], SCREEN_SIZE), Buttons::RIGHT);
+        assert_eq!(buttons_at_positions([JUMP], SCREEN_SIZE), Buttons::JUMP);
+    }
+
+    #[test]
+    fn test_multiple_touches_combine_buttons() {
+        assert_eq!(
+            buttons_at_positions([RIGHT, JUMP], SCREEN_SIZE),
+            Buttons::RIGHT | Buttons::JUMP
+        );
+        assert_eq!(
+            buttons_at_positions([LEFT, vec2(400., 100.), JUMP], SCREEN_SIZE),
+            Buttons::LEFT | Buttons::JUMP
+        );
+    }
+
+    #[test]
+    fn test_zones_scale_with_screen_size() {
+        let screen_size = SCREEN_SIZE * 2.;
+        assert_eq!(
+            buttons_at_positions([JUMP * 2.], screen_size),
+            Buttons::JUMP
+        );
+        assert_eq!(buttons_at_positions([JUMP], screen_size), Buttons::empty());
+    }
+}
@@ -1,8 +1,11 @@
 use anyhow::Result;
-use macroquad::prelude::{load_string, Rect, Vec2};
+use macroquad::prelude::{Rect, Vec2};
 use std::{collections::HashMap, rc::Rc};
 
 use crate::{
+    asset_loader::AssetLoader,
+    checksum::digest,
+    context::GameContext,
     ldtk,
     level::{EntityKind, Level},
 };
@@ -11,24 +14,83 @@ use crate::{
 const EXPECTED_JSON_VERSION: &str = "1.2.5";
 
 pub struct World {
+    ctx: Rc<GameContext>,
     levels: HashMap<String, Rc<Level>>,
+    /// All the levels, in the order they're defined in the LDtk file.
+    ordered_levels: Vec<Rc<Level>>,
+    digest: u64,
+    /// The total number of gems the player can collect from each level, keyed
+    /// by the level's identifier. See `total_gems()`.
+    total_gems: HashMap<String, u32>,
 }
 
 impl World {
-    pub async fn load(path: &str) -> Result<Self> {
-        let world_json = load_string(&path).await?;
-        let world: ldtk::Coordinate = serde_json::from_str(world_json.as_str())?;
+    pub async fn load(path: &str, ctx: Rc<GameContext>, loader: &AssetLoader) -> Result<Self> {
+        let world_json = loader.load_string(path).await?;
+        World::from_json(&world_json, ctx)
+    }
+
+    /// Create a world from the contents of an LDtk file.
+    pub fn from_json(world_json: &str, ctx: Rc<GameContext>) -> Result<Self> {
+        let digest = digest(world_json.as_bytes());
+        let world: ldtk::Coordinate = serde_json::from_str(world_json)?;
         if world.json_version != EXPECTED_JSON_VERSION {
             eprintln!("WARNING: Expected LDtk json_version {}, got {}. Please update EXPECTED_JSON_VERSION if needed.", EXPECTED_JSON_VERSION, world.json_version);
         }
         let mut levels = HashMap::with_capacity(world.levels.len());
+        let mut ordered_levels = Vec::with_capacity(world.levels.len());
 
         for ldtk_level in world.levels {
-            let level = Level::from_ldtk(ldtk_level)?;
-            levels.insert(level.identifier.clone(), Rc::new(level));
+            let level = Rc::new(Level::from_ldtk(ldtk_level, &ctx)?);
+            levels.insert(level.identifier.clone(), level.clone());
+            ordered_levels.push(level);
         }
+        let total_gems = count_reachable_gems(&ordered_levels);
+
+        Ok(World {
+            ctx,
+            levels,
+            ordered_levels,
+            digest,
+            total_gems,
+        })
+    }
 
-        Ok(World { levels })
+    /// The context the world was loaded with. Since levels are scaled by
+    /// the config's sprite scale, a world only makes sense with its context.
+    pub fn ctx(&self) -> &Rc<GameContext> {
+        &self.ctx
+    }
+
+    /// A digest of the world file's contents, used to identify which version
+    /// of the world a recording was made with.
+    pub fn digest(&self) -> u64 {
+        self.digest
+    }
+
+    /// The number of gems in all the levels that can be reached from the given
+    /// one, i.e. all the gems the player could collect from there.
+    pub fn total_gems(&self, level: &Level) -> u32 {
+        self.total_gems.get(&level.identifier).copied().unwrap_or(0)
+    }
+
+    /// Iterate through all the levels in the world, in the order they're
+    /// defined in the LDtk file.
+    pub fn levels(&self) -> impl Iterator<Item = &Rc<Level>> {
+        self.ordered_levels.iter()
+    }
+
+    /// The position of the given level in the LDtk file. Since levels are
+    /// generally defined in the order the player is expected to reach them,
+    /// this can be used as a rough measure of progress.
+    pub fn level_index(&self, identifier: &str) -> Option<usize> {
+        self.ordered_levels
+            .iter()
+            .position(|level| level.identifier == identifier)
+    }
+
+    pub fn level(&self, identifier: &str) -> Option<Rc<Level>> {
+        self.levels.get(identifier).cloned()
     }
 
     pub fn player_start(&self, name: &str) -> Option<(Rc<Level>, Rect)> {
@@ -65,3 +127,62 @@ impl World {
         None
     }
 }
+
+/// Returns the total number of gems that can be collected from each of the
+/// given levels, keyed by level identifier.
+///
+/// The player can only move between levels that touch each other in the world,
+/// so levels that are off on their own (like the dev levels) have their own
+/// totals.
+fn count_reachable_gems(levels: &[Rc<Level>]) -> HashMap<String, u32> {
+    let mut group_of_level = vec![None; levels.len()];
+    let mut group_gems = vec![];
+    for start in 0..levels.len() {
+        if group_of_level[start].is_some() {
+            continue;
+        }
+        let group = group_gems.len();
+        let mut gems = 0;
+        let mut to_visit = vec![start];
+        group_of_level[start] = Some(group);
+        while let Some(index) = to_visit.pop() {
+            let level = &levels[index];
+            gems += level
+                .entities
+                .values()
+                .filter(|entity| matches!(entity.kind, EntityKind::Gem))
+                .count() as u32;
+            for (other_index, other) in levels.iter().enumerate() {
+                if group_of_level[other_index].is_none()
+                    && level.world_rect.overlaps(&other.world_rect)
+                {
+                    group_of_level[other_index] = Some(group);
+                    to_visit.push(other_index);
+                }
+            }
+        }
+        group_gems.push(gems);
+    }
+    levels
+        .iter()
+        .zip(group_of_level)
+        .map(|(level, group)| {
+            let group = group.expect("Every level should be in a group");
+            (level.identifier.clone(), group_gems[group])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{headless::load_headless, CONFIG_PATH, WORLD_PATH};
+
+    #[test]
+    fn test_total_gems_only_counts_reachable_levels() {
+        let world = load_headless(CONFIG_PATH, WORLD_PATH).unwrap();
+        let total_gems = |identifier| world.total_gems(&world.level(identifier).unwrap());
+        assert_eq!(total_gems("Level_0"), 6);
+        assert_eq!(total_gems("Dev_Level_0"), 1);
+        assert_eq!(total_gems("Dev_Level_1"), 1);
+    }
+}
@@ -1,8 +1,8 @@
 use std::cell::RefCell;
 
-use crate::{entity::EntityMap, level::Level};
+use crate::{context::GameContext, entity::EntityMap, level::Level};
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
 pub struct ZIndexComponent {
     value: i32,
 }
@@ -38,11 +38,11 @@ impl ZIndexedDrawingSystem {
         entity_z_indices.sort_by(|a, b| a.1.cmp(&b.1));
     }
 
-    pub fn draw_entities(&self, entities: &EntityMap, level: &Level) {
+    pub fn draw_entities(&self, entities: &EntityMap, level: &Level, ctx: &GameContext) {
         self.update_entity_z_indices(entities);
         for (id, _) in self.entity_z_indices.borrow().iter() {
             let entity = &entities.get(*id).unwrap();
-            entity.sprite.draw_current_frame(level);
+            entity.sprite.draw_current_frame(level, ctx);
         }
     }
 }
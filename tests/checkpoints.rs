@@ -0,0 +1,66 @@
+mod common;
+
+use common::{add_entity, load_world_from_json, load_world_json, DEV_PLATFORM_START};
+use macroquad_fun::{input::Buttons, level_runtime::FrameResult, simulation::Simulation};
+
+#[test]
+fn test_players_respawn_at_checkpoints() {
+    // Put a checkpoint on the ledge the player starts on, and a spear
+    // below it.
+    let mut world_json = load_world_json();
+    add_entity(
+        &mut world_json,
+        "Dev_Level_1",
+        "Checkpoint",
+        "55c9e95a-ca6b-11f1-8494-02fc00000001",
+        [0, 32],
+        [48, 32],
+    );
+    add_entity(
+        &mut world_json,
+        "Dev_Level_1",
+        "Spear",
+        "55d0a5d8-ca6b-11f1-8494-02fc00000001",
+        [72, 40],
+        [5, 42],
+    );
+    let world = load_world_from_json(&world_json);
+    let mut simulation = Simulation::new(DEV_PLATFORM_START, world);
+
+    // The player starts out touching the checkpoint.
+    simulation.advance_one_frame(Buttons::default());
+    let checkpoint_pos = simulation
+        .level_runtime()
+        .entities()
+        .main_player()
+        .sprite
+        .pos;
+    let count_pickups = |simulation: &Simulation| {
+        simulation
+            .level_runtime()
+            .entities()
+            .iter()
+            .filter(|(_id, entity)| entity.pickup.is_some())
+            .count()
+    };
+    assert_eq!(count_pickups(&simulation), 1);
+
+    // Walk off the ledge, grabbing the spear on the way down to our doom.
+    let mut died = false;
+    for _ in 0..300 {
+        if simulation.advance_one_frame(Buttons::RIGHT) == FrameResult::MainPlayerDied {
+            died = true;
+            break;
+        }
+    }
+    assert!(died, "Player never died");
+
+    let player = simulation.level_runtime().entities().main_player();
+    assert_eq!(simulation.level_runtime().level().identifier, "Dev_Level_1");
+    assert_eq!(player.sprite.pos, checkpoint_pos);
+    assert_eq!(player.physics.velocity.x, 0.);
+    assert_eq!(player.physics.velocity.y, 0.);
+    // Anything picked up after reaching the checkpoint is lost.
+    assert!(!player.player.unwrap().inventory.has_spear);
+    assert_eq!(count_pickups(&simulation), 1);
+}
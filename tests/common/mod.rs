@@ -0,0 +1,96 @@
+//! Setup shared by the integration tests.
+//!
+//! The tests play through the shipped world in `media/world.ldtk`, so they
+//! depend on its contents. The start positions they use are described below,
+//! along with what the tests expect to find around them.
+
+// Each test file only uses some of these.
+#![allow(dead_code)]
+
+use std::rc::Rc;
+
+use macroquad_fun::{
+    headless::load_headless, simulation::Simulation, world::World, CONFIG_PATH, WORLD_PATH,
+};
+use serde_json::{json, Value};
+
+/// Where the game normally starts: on the ground in `Level_0`.
+pub const DEFAULT_START: &str = "default";
+
+/// A ledge in `Dev_Level_1`, with a moving platform below it to the right and
+/// `Dev_Level_0` to the left. The bottom of the level is open, so the player
+/// can fall to their death.
+pub const DEV_PLATFORM_START: &str = "dev_platform";
+
+/// Load the shipped config and world, without needing a window.
+pub fn load_world() -> Rc<World> {
+    load_headless(CONFIG_PATH, WORLD_PATH).unwrap()
+}
+
+/// Start a new game at the given start position in the shipped world.
+pub fn new_simulation(start_position: &str) -> Simulation {
+    Simulation::new(start_position, load_world())
+}
+
+/// Parse the shipped world file, so that a test can change it before loading it
+/// with `load_world_from_json()`.
+pub fn load_world_json() -> Value {
+    serde_json::from_str(&std::fs::read_to_string(WORLD_PATH).unwrap()).unwrap()
+}
+
+/// Load the given world with the shipped config.
+pub fn load_world_from_json(world_json: &Value) -> Rc<World> {
+    let ctx = load_world().ctx().clone();
+    Rc::new(World::from_json(&world_json.to_string(), ctx).unwrap())
+}
+
+/// Add an entity with the given identifier to the given level, at the given
+/// position and size in unscaled pixels. The entity can't have any fields.
+///
+/// This lets tests set up the situations they need without the shipped world
+/// having to contain them.
+pub fn add_entity(
+    world_json: &mut Value,
+    level: &str,
+    identifier: &str,
+    iid: &str,
+    px: [i64; 2],
+    size: [i64; 2],
+) {
+    let def = world_json["defs"]["entities"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|def| def["identifier"] == identifier)
+        .unwrap_or_else(|| panic!("Entity {} is not defined", identifier))
+        .clone();
+    let layer = world_json["levels"]
+        .as_array_mut()
+        .unwrap()
+        .iter_mut()
+        .find(|candidate| candidate["identifier"] == level)
+        .unwrap_or_else(|| panic!("Level {} not found", level))["layerInstances"]
+        .as_array_mut()
+        .unwrap()
+        .iter_mut()
+        .find(|layer| layer["__identifier"] == "Entities")
+        .unwrap();
+    let grid_size = layer["__gridSize"].as_i64().unwrap();
+    layer["entityInstances"]
+        .as_array_mut()
+        .unwrap()
+        .push(json!({
+            "__identifier": identifier,
+            "__grid": [px[0] / grid_size, px[1] / grid_size],
+            "__pivot": [0, 0],
+            "__tags": [],
+            "__tile": null,
+            "__smartColor": def["color"],
+            "iid": iid,
+            "width": size[0],
+            "height": size[1],
+            "defUid": def["uid"],
+            "px": px,
+            "fieldInstances": [],
+        }));
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use common::{load_world, DEV_PLATFORM_START};
+use macroquad_fun::{
+    checksum::ChecksumChannel,
+    input::Buttons,
+    recorder::{InputPlayer, RecordedFrame, Recording, RecordingMetadata},
+    simulation::Simulation,
+};
+
+/// A recording that wanders back and forth between levels, jumping a lot.
+fn make_recording(metadata: RecordingMetadata) -> Recording {
+    let frame = |frame_number, buttons| RecordedFrame {
+        frame_number,
+        buttons,
+    };
+    Recording {
+        metadata: Some(metadata),
+        frames: vec![
+            frame(0, Buttons::LEFT),
+            frame(90, Buttons::RIGHT | Buttons::JUMP),
+            frame(200, Buttons::RIGHT),
+            frame(230, Buttons::RIGHT | Buttons::JUMP),
+            frame(300, Buttons::LEFT | Buttons::JUMP),
+            frame(380, Buttons::LEFT),
+            frame(450, Buttons::default()),
+        ],
+        checksums: vec![],
+    }
+}
+
+/// Replay the recording in a freshly-loaded world, returning the serialized
+/// end state of the game.
+fn replay() -> String {
+    let world = load_world();
+    let metadata = RecordingMetadata::current(DEV_PLATFORM_START, &world);
+    let checksums = ChecksumChannel::new_shared(0);
+    let input_player =
+        InputPlayer::from_recording(make_recording(metadata.clone()), &metadata, checksums)
+            .unwrap();
+    let mut simulation = Simulation::new(DEV_PLATFORM_START, world.clone());
+    simulation.run(input_player);
+    serde_json::to_string(&simulation.level_runtime().save()).unwrap()
+}
+
+#[test]
+fn test_replaying_a_recording_twice_ends_in_the_same_state() {
+    assert_eq!(replay(), replay());
+}
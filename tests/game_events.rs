@@ -0,0 +1,42 @@
+mod common;
+
+use common::{new_simulation, DEV_PLATFORM_START};
+use macroquad_fun::{
+    entity::MAIN_PLAYER_ENTITY_ID, events::GameEvent, input::Buttons, simulation::Simulation,
+};
+
+fn advance_collecting_events(
+    simulation: &mut Simulation,
+    buttons: Buttons,
+    frames: u32,
+) -> Vec<GameEvent> {
+    let mut events = vec![];
+    for _ in 0..frames {
+        simulation.advance_one_frame(buttons);
+        events.extend_from_slice(simulation.level_runtime().events());
+    }
+    events
+}
+
+#[test]
+fn test_events_are_emitted_for_jumps_landings_and_level_changes() {
+    let mut simulation = new_simulation(DEV_PLATFORM_START);
+
+    // Let the player settle on the ground before doing anything.
+    advance_collecting_events(&mut simulation, Buttons::default(), 60);
+
+    let events = advance_collecting_events(&mut simulation, Buttons::JUMP, 1);
+    assert!(events.contains(&GameEvent::PlayerJumped {
+        id: MAIN_PLAYER_ENTITY_ID
+    }));
+    let events = advance_collecting_events(&mut simulation, Buttons::default(), 120);
+    assert!(events.contains(&GameEvent::PlayerLanded {
+        id: MAIN_PLAYER_ENTITY_ID
+    }));
+
+    let events = advance_collecting_events(&mut simulation, Buttons::LEFT, 120);
+    assert!(events.contains(&GameEvent::LevelChanged {
+        from: "Dev_Level_1".to_owned(),
+        to: "Dev_Level_0".to_owned(),
+    }));
+}
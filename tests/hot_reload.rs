@@ -0,0 +1,61 @@
+mod common;
+
+use common::{add_entity, load_world_from_json, load_world_json, DEV_PLATFORM_START};
+use macroquad_fun::{
+    input::Buttons, level_runtime::LevelRuntime, simulation::Simulation, world::World,
+};
+use std::rc::Rc;
+use uuid::Uuid;
+
+const CHECKPOINT_IID: &str = "55c9e95a-ca6b-11f1-8494-02fc00000001";
+
+/// The shipped world, with a checkpoint at the given x-coordinate on the ledge
+/// that the player starts on.
+fn world_json_with_checkpoint_at(x: i64) -> serde_json::Value {
+    let mut world_json = load_world_json();
+    add_entity(
+        &mut world_json,
+        "Dev_Level_1",
+        "Checkpoint",
+        CHECKPOINT_IID,
+        [x, 32],
+        [16, 32],
+    );
+    world_json
+}
+
+fn entity_x(runtime: &LevelRuntime, iid: Uuid) -> f32 {
+    let (_id, entity) = runtime
+        .entities()
+        .iter()
+        .find(|(_id, entity)| entity.iid == Some(iid))
+        .unwrap();
+    entity.sprite.pos.x
+}
+
+#[test]
+fn test_reloading_world_keeps_main_player_position() {
+    let world = load_world_from_json(&world_json_with_checkpoint_at(0));
+    let mut simulation = Simulation::new(DEV_PLATFORM_START, world.clone());
+    for _ in 0..30 {
+        simulation.advance_one_frame(Buttons::RIGHT);
+    }
+    let runtime = simulation.level_runtime();
+    let ctx = world.ctx().clone();
+    let new_world_json = world_json_with_checkpoint_at(16).to_string();
+    let new_world = World::from_json(&new_world_json, ctx).unwrap();
+    let grid_size = new_world
+        .level(&runtime.level().identifier)
+        .unwrap()
+        .grid_size;
+
+    let reloaded = runtime.reload(Rc::new(new_world)).unwrap();
+
+    assert_eq!(reloaded.level().identifier, runtime.level().identifier);
+    assert_eq!(
+        reloaded.entities().main_player().sprite.pos,
+        runtime.entities().main_player().sprite.pos
+    );
+    let iid = Uuid::parse_str(CHECKPOINT_IID).unwrap();
+    assert_eq!(entity_x(&reloaded, iid), entity_x(runtime, iid) + grid_size);
+}
@@ -0,0 +1,61 @@
+use std::rc::Rc;
+
+mod common;
+
+use common::{load_world, DEFAULT_START};
+use macroquad_fun::{input::Buttons, simulation::Simulation, world::World};
+
+/// Jump, then press jump again on the given frame of the jump (if any), returning
+/// the player's vertical velocity after each frame.
+fn jump(simulation: &mut Simulation, frames: usize, jump_again_at: Option<usize>) -> Vec<f32> {
+    (0..frames)
+        .map(|i| {
+            let buttons = if i == 0 || Some(i) == jump_again_at {
+                Buttons::JUMP
+            } else {
+                Buttons::empty()
+            };
+            simulation.advance_one_frame(buttons);
+            simulation
+                .level_runtime()
+                .entities()
+                .main_player()
+                .physics
+                .velocity
+                .y
+        })
+        .collect()
+}
+
+fn new_simulation_on_ground(world: &Rc<World>) -> Simulation {
+    let mut simulation = Simulation::new(DEFAULT_START, world.clone());
+    for _ in 0..30 {
+        simulation.advance_one_frame(Buttons::empty());
+    }
+    simulation
+}
+
+#[test]
+fn test_jump_pressed_just_before_landing_is_buffered() {
+    let world = load_world();
+
+    let velocities = jump(&mut new_simulation_on_ground(&world), 60, None);
+    let landing_frame = velocities
+        .iter()
+        .enumerate()
+        .skip(1)
+        .position(|(i, &y)| y == 0. && velocities[i - 1] > 0.)
+        .expect("The player should land")
+        + 1;
+    assert!(velocities[landing_frame..].iter().all(|&y| y == 0.));
+
+    let velocities = jump(
+        &mut new_simulation_on_ground(&world),
+        60,
+        Some(landing_frame - 2),
+    );
+    assert!(
+        velocities[landing_frame..].iter().any(|&y| y < 0.),
+        "The player should jump again after landing"
+    );
+}
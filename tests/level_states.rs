@@ -0,0 +1,89 @@
+mod common;
+
+use common::{add_entity, load_world_from_json, load_world_json, DEV_PLATFORM_START};
+use macroquad_fun::{input::Buttons, simulation::Simulation};
+use uuid::Uuid;
+
+fn advance_until_level(simulation: &mut Simulation, buttons: Buttons, identifier: &str) {
+    for _ in 0..120 {
+        simulation.advance_one_frame(buttons);
+        if simulation.level_runtime().level().identifier == identifier {
+            return;
+        }
+    }
+    panic!("Never reached level {}", identifier);
+}
+
+fn level_entity_ids(simulation: &Simulation) -> Vec<(Uuid, u64)> {
+    let mut ids: Vec<(Uuid, u64)> = simulation
+        .level_runtime()
+        .entities()
+        .iter()
+        .filter_map(|(id, entity)| entity.iid.map(|iid| (iid, id)))
+        .collect();
+    ids.sort();
+    ids
+}
+
+fn has_spear(simulation: &Simulation) -> bool {
+    let player = simulation.level_runtime().entities().main_player();
+    player.player.unwrap().inventory.has_spear
+}
+
+fn count_pickups(simulation: &Simulation) -> usize {
+    simulation
+        .level_runtime()
+        .entities()
+        .iter()
+        .filter(|(_id, entity)| entity.pickup.is_some())
+        .count()
+}
+
+#[test]
+fn test_levels_are_restored_when_reentered() {
+    // Put a spear below the ledge the player starts on.
+    let mut world_json = load_world_json();
+    add_entity(
+        &mut world_json,
+        "Dev_Level_1",
+        "Spear",
+        "55d0a5d8-ca6b-11f1-8494-02fc00000001",
+        [72, 40],
+        [5, 42],
+    );
+    let world = load_world_from_json(&world_json);
+    let mut simulation = Simulation::new(DEV_PLATFORM_START, world);
+    assert_eq!(simulation.level_runtime().level().identifier, "Dev_Level_1");
+    assert_eq!(count_pickups(&simulation), 1);
+
+    // Grab the spear on the way down from the ledge and land on the moving
+    // platform, then ride it for a bit.
+    for _ in 0..30 {
+        simulation.advance_one_frame(Buttons::RIGHT);
+    }
+    for _ in 0..60 {
+        simulation.advance_one_frame(Buttons::default());
+    }
+    assert_eq!(simulation.level_runtime().level().identifier, "Dev_Level_1");
+    assert!(has_spear(&simulation));
+    assert_eq!(count_pickups(&simulation), 0);
+    let original_ids = level_entity_ids(&simulation);
+    assert!(!original_ids.is_empty());
+
+    advance_until_level(
+        &mut simulation,
+        Buttons::LEFT | Buttons::JUMP,
+        "Dev_Level_0",
+    );
+    advance_until_level(
+        &mut simulation,
+        Buttons::RIGHT | Buttons::JUMP,
+        "Dev_Level_1",
+    );
+
+    // If the level's entities had been respawned, they would have new ids,
+    // and the spear would be back.
+    assert_eq!(level_entity_ids(&simulation), original_ids);
+    assert_eq!(count_pickups(&simulation), 0);
+    assert!(has_spear(&simulation));
+}
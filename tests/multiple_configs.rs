@@ -0,0 +1,56 @@
+use std::rc::Rc;
+
+mod common;
+
+use common::{load_world, DEFAULT_START};
+use macroquad_fun::{
+    cli::Cli, config::parse_config, input::Buttons, simulation::Simulation, world::World,
+    CONFIG_PATH, WORLD_PATH,
+};
+
+/// Jump and return how high the main player got.
+fn max_jump_height(simulation: &mut Simulation) -> f32 {
+    let start_y = simulation
+        .level_runtime()
+        .entities()
+        .main_player()
+        .sprite
+        .pos
+        .y;
+    let mut min_y = start_y;
+    for _ in 0..60 {
+        simulation.advance_one_frame(Buttons::JUMP);
+        min_y = min_y.min(
+            simulation
+                .level_runtime()
+                .entities()
+                .main_player()
+                .sprite
+                .pos
+                .y,
+        );
+    }
+    start_y - min_y
+}
+
+#[test]
+fn test_simulations_with_different_configs_can_coexist() {
+    let mut config: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(CONFIG_PATH).unwrap()).unwrap();
+    let gravity = config["gravity"].as_f64().unwrap();
+    config["gravity"] = (gravity * 2.).into();
+    let heavy_config = parse_config(&config.to_string(), &Cli::default()).unwrap();
+
+    let world = load_world();
+    let heavy_ctx = world.ctx().with_config(heavy_config);
+    let world_json = std::fs::read_to_string(WORLD_PATH).unwrap();
+    let heavy_world = Rc::new(World::from_json(&world_json, heavy_ctx).unwrap());
+    let mut simulation = Simulation::new(DEFAULT_START, world);
+    let mut heavy_simulation = Simulation::new(DEFAULT_START, heavy_world);
+
+    let height = max_jump_height(&mut simulation);
+    let heavy_height = max_jump_height(&mut heavy_simulation);
+
+    assert!(heavy_height > 0.);
+    assert!(heavy_height < height);
+}
@@ -0,0 +1,22 @@
+mod common;
+
+use common::{load_world, DEFAULT_START};
+use macroquad_fun::{
+    input::Buttons,
+    level_runtime::{LevelRuntime, SavedLevelRuntime},
+    simulation::Simulation,
+};
+
+#[test]
+fn test_saved_games_survive_serialization() {
+    let world = load_world();
+    let mut simulation = Simulation::new(DEFAULT_START, world.clone());
+    for _ in 0..120 {
+        simulation.advance_one_frame(Buttons::RIGHT);
+    }
+    let original = simulation.level_runtime();
+    let json = serde_json::to_string(&original.save()).unwrap();
+    let saved: SavedLevelRuntime = serde_json::from_str(&json).unwrap();
+    let loaded = LevelRuntime::from_saved(saved, world.clone()).unwrap();
+    assert_eq!(loaded.checksum(), original.checksum());
+}